@@ -0,0 +1,316 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// Arbitrary-precision signed integer, used once a value no longer fits in an `i64`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    /// Little-endian base 2^32 limbs without trailing zeros. Zero has no limbs.
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    /// Parses a decimal integer with an optional leading `-`.
+    pub fn parse(text: &str) -> Option<Self> {
+        Self::parse_radix(text, 10)
+    }
+
+    /// Parses an integer in base `radix`, from 2 to 36, with an optional leading `-`.
+    pub fn parse_radix(text: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut magnitude = Vec::new();
+
+        for ch in digits.chars() {
+            mul_add_small(&mut magnitude, radix, ch.to_digit(radix)?);
+        }
+
+        Some(Self::new(negative, magnitude))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+
+        let value = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0i128, |acc, limb| (acc << 32) | *limb as i128);
+
+        i64::try_from(if self.negative { -value } else { value }).ok()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let value = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * 4294967296.0 + *limb as f64);
+
+        if self.negative { -value } else { value }
+    }
+
+    /// Division truncating towards zero. `None` when dividing by zero.
+    pub fn checked_div(&self, other: &Self) -> Option<Self> {
+        let (quotient, _) = div_rem(&self.magnitude, &other.magnitude)?;
+
+        Some(Self::new(self.negative != other.negative, quotient))
+    }
+
+    /// Remainder with the sign of the dividend. `None` when dividing by zero.
+    pub fn checked_rem(&self, other: &Self) -> Option<Self> {
+        let (_, remainder) = div_rem(&self.magnitude, &other.magnitude)?;
+
+        Some(Self::new(self.negative, remainder))
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let abs = value.unsigned_abs();
+
+        Self::new(value < 0, vec![abs as u32, (abs >> 32) as u32])
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: Self) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitudes(&self.magnitude, &other.magnitude));
+        }
+
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitudes(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitudes(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: Self) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: Self) -> BigInt {
+        let mut product = vec![0u32; self.magnitude.len() + other.magnitude.len()];
+
+        for (i, a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0u64;
+
+            for (j, b) in other.magnitude.iter().enumerate() {
+                let value = product[i + j] as u64 + *a as u64 * *b as u64 + carry;
+                product[i + j] = value as u32;
+                carry = value >> 32;
+            }
+
+            product[i + other.magnitude.len()] = carry as u32;
+        }
+
+        BigInt::new(self.negative != other.negative, product)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off nine decimal digits at a time, least significant first.
+        let mut magnitude = self.magnitude.clone();
+        let mut chunks = Vec::new();
+
+        while !magnitude.is_empty() {
+            chunks.push(div_rem_small(&mut magnitude, 1_000_000_000));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        let mut chunks = chunks.iter().rev();
+
+        if let Some(first) = chunks.next() {
+            write!(f, "{first}")?;
+        }
+
+        for chunk in chunks {
+            write!(f, "{chunk:09}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+
+    for i in 0..a.len().max(b.len()) {
+        let value = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(value as u32);
+        carry = value >> 32;
+    }
+
+    sum.push(carry as u32);
+    trim(&mut sum);
+
+    sum
+}
+
+/// `a - b` where `a >= b`.
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+
+    for (i, limb) in a.iter().enumerate() {
+        let mut value = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+
+        if value < 0 {
+            value += 1 << 32;
+            borrow = 1;
+        }
+
+        difference.push(value as u32);
+    }
+
+    trim(&mut difference);
+
+    difference
+}
+
+fn mul_add_small(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+    let mut carry = addend as u64;
+
+    for limb in magnitude.iter_mut() {
+        let value = *limb as u64 * factor as u64 + carry;
+        *limb = value as u32;
+        carry = value >> 32;
+    }
+
+    if carry != 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// Divides `magnitude` in place and returns the remainder.
+fn div_rem_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+
+    for limb in magnitude.iter_mut().rev() {
+        let value = (remainder << 32) | *limb as u64;
+        *limb = (value / divisor as u64) as u32;
+        remainder = value % divisor as u64;
+    }
+
+    trim(magnitude);
+
+    remainder as u32
+}
+
+/// Schoolbook binary long division. `None` when `divisor` is zero.
+fn div_rem(dividend: &[u32], divisor: &[u32]) -> Option<(Vec<u32>, Vec<u32>)> {
+    match divisor {
+        [] => None,
+        [small] => {
+            let mut quotient = dividend.to_vec();
+            let remainder = div_rem_small(&mut quotient, *small);
+
+            Some((quotient, vec![remainder]))
+        }
+        _ => {
+            let mut quotient = vec![0u32; dividend.len()];
+            let mut remainder: Vec<u32> = Vec::new();
+
+            for bit in (0..dividend.len() * 32).rev() {
+                mul_add_small(&mut remainder, 2, (dividend[bit / 32] >> (bit % 32)) & 1);
+
+                if compare_magnitudes(&remainder, divisor) != Ordering::Less {
+                    remainder = sub_magnitudes(&remainder, divisor);
+                    quotient[bit / 32] |= 1 << (bit % 32);
+                }
+            }
+
+            trim(&mut quotient);
+
+            Some((quotient, remainder))
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::io::{self, Write};
+
+use crate::{
+    bigint::BigInt,
+    interpreter::{RuntimeError, RuntimeErrorKind, Scope, Value},
+    native::register,
+    number::Number,
+};
+
+fn join(arguments: &[Value]) -> String {
+    arguments
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Writes `text` to stdout at once, so that a failure is reported by the call that caused it.
+fn write(text: &str) -> Result<Value, RuntimeError> {
+    let mut stdout = io::stdout().lock();
+
+    stdout
+        .write_all(text.as_bytes())
+        .and_then(|()| stdout.flush())
+        .map_err(|e| RuntimeError::new(RuntimeErrorKind::Output(e.kind())))?;
+
+    Ok(Value::Nil)
+}
+
+fn print(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    write(&join(arguments))
+}
+
+fn println(arguments: &[Value]) -> Result<Value, RuntimeError> {
+    write(&(join(arguments) + "\n"))
+}
+
+fn conversion_error(value: &Value, target: &'static str) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::InvalidConversion {
+        value: format!("{value:?}"),
+        target,
+    })
+}
+
+/// Truncates floats towards zero and parses strings. Fails if the value does not fit in an `i64`.
+fn int_from(value: Value) -> Result<i64, RuntimeError> {
+    let int = match &value {
+        Value::Number(Number::Int(a)) => Some(*a),
+        Value::Number(Number::BigInt(a)) => a.to_i64(),
+        // `i64::MAX as f64` rounds up to 2^63, which is already out of range.
+        Value::Number(Number::Float(a)) if a.is_finite() && *a >= i64::MIN as f64 && *a < i64::MAX as f64 => {
+            Some(*a as i64)
+        }
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+
+    int.ok_or_else(|| conversion_error(&value, "int"))
+}
+
+fn float_from(value: Value) -> Result<f64, RuntimeError> {
+    let float = match &value {
+        Value::Number(number) => Some(number.to_f64()),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+
+    float.ok_or_else(|| conversion_error(&value, "float"))
+}
+
+fn bigint_from(value: Value) -> Result<Number, RuntimeError> {
+    let bigint = match &value {
+        Value::Number(Number::Int(a)) => Some(BigInt::from(*a)),
+        Value::Number(Number::BigInt(a)) => Some(a.clone()),
+        Value::Number(Number::Float(a)) if a.is_finite() => BigInt::parse(&format!("{:.0}", a.trunc())),
+        Value::String(s) => BigInt::parse(s.trim()),
+        _ => None,
+    };
+
+    bigint
+        .map(Number::BigInt)
+        .ok_or_else(|| conversion_error(&value, "bigint"))
+}
+
+/// Defines the standard global functions and modules in `scope`.
+pub fn install(scope: &mut Scope) {
+    register(scope, "print", print);
+    register(scope, "println", println);
+
+    register(scope, "str.from", |value: Value| value.to_string());
+    register(scope, "int.from", int_from);
+    register(scope, "float.from", float_from);
+    register(scope, "bigint.from", bigint_from);
+}
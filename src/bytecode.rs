@@ -0,0 +1,97 @@
+use std::rc::Rc;
+
+use crate::{interpreter::Value, number::Operation, span::Span};
+
+/// A single VM instruction. Operands index into the owning `Chunk`:
+/// `constants`, `names`, `functions`, local slots of the current frame or
+/// the upvalues of the running closure. Jump targets are instruction indices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Constant(usize),
+    Nil,
+    True,
+    False,
+    Pop,
+
+    GetLocal(usize),
+    SetLocal(usize),
+    DefineLocal(usize),
+    /// Locals captured by a nested function live in shared cells instead of stack slots.
+    GetCell(usize),
+    SetCell(usize),
+    DefineCell(usize),
+    GetUpvalue(usize),
+    SetUpvalue(usize),
+    GetGlobal(usize),
+    SetGlobal(usize),
+    DefineGlobal(usize),
+    GetAttribute(usize),
+    /// Pops a value and then the object to store it in.
+    SetAttribute(usize),
+
+    Arithmetic(Operation),
+    Negate,
+    Not,
+    /// Replaces the top of the stack with its truthiness.
+    ToBool,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Range,
+    Array(usize),
+    /// Joins the top `n` values into a string, for interpolation.
+    Concat(usize),
+
+    Jump(usize),
+    /// Pops the condition.
+    JumpIfFalse(usize),
+    JumpIfTrue(usize),
+
+    /// Pops an iterable and starts iterating over it.
+    IterStart,
+    /// Pushes the next item, or finishes the iteration and jumps once it is exhausted.
+    IterNext(usize),
+    /// Finishes the innermost iteration early, for `break`.
+    IterEnd,
+
+    Closure(usize),
+    Call(usize),
+    Return,
+}
+
+/// Where a closure finds a captured variable when it is created.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capture {
+    /// A cell in the enclosing function's frame.
+    Local(usize),
+    /// One of the enclosing closure's own upvalues.
+    Upvalue(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    /// Source span of each instruction, for runtime errors.
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>,
+    /// Variable and attribute names.
+    pub names: Vec<String>,
+    /// Functions declared directly inside this one.
+    pub functions: Vec<Rc<Prototype>>,
+}
+
+/// A compiled function, ready to be turned into a closure.
+#[derive(Debug)]
+pub struct Prototype {
+    pub name: String,
+    pub arity: usize,
+    /// Number of local slots a call reserves.
+    pub slots: usize,
+    /// Whether each parameter slot is captured and must start out as a cell.
+    pub captured_parameters: Vec<bool>,
+    pub captures: Vec<Capture>,
+    pub chunk: Chunk,
+}
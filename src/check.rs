@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use crate::{
+    builtins,
+    diagnostics::Diagnostic,
+    interpreter::Scope,
+    parser::{Node, NodeKind, Part},
+};
+
+/// Looks for mistakes that can be found without running the program.
+///
+/// For now this reports variables that are used but declared nowhere: not by a `let`,
+/// a `func`, a parameter, a loop variable or a builtin. Scoping is ignored, so a name
+/// declared anywhere counts as declared everywhere and nothing valid is ever reported.
+pub fn check(program: &Node, globals: &[&str]) -> Vec<Diagnostic> {
+    let mut builtins = Scope::default();
+    builtins::install(&mut builtins);
+
+    let mut declared: HashSet<&str> = globals.iter().copied().collect();
+    let mut used = Vec::new();
+    walk(program, &mut declared, &mut used);
+
+    used.into_iter()
+        .filter(|(name, _)| !declared.contains(name) && builtins.get(name).is_none())
+        .map(|(name, node)| {
+            Diagnostic::error(format!("undefined variable `{name}`"))
+                .with_span(node.span)
+                .with_label("not declared anywhere in this program")
+        })
+        .collect()
+}
+
+/// Collects every declared name and every variable reference below `node`. The
+/// recursion is bounded by the parser's `MAX_NESTING`.
+fn walk<'a>(node: &'a Node, declared: &mut HashSet<&'a str>, used: &mut Vec<(&'a str, &'a Node)>) {
+    if let Some((_, lhs, rhs)) = node.binary() {
+        walk(lhs, declared, used);
+        walk(rhs, declared, used);
+        return;
+    }
+
+    match &node.kind {
+        NodeKind::Ident(name) => used.push((name, node)),
+        NodeKind::Number(_) | NodeKind::String(_) | NodeKind::Break(_) | NodeKind::Continue(_) => {}
+        NodeKind::Interpolated(parts) => {
+            for part in parts {
+                if let Part::Expression(expression) = part {
+                    walk(expression, declared, used);
+                }
+            }
+        }
+        NodeKind::Assignment { name, value, .. } => {
+            declared.insert(name);
+            walk(value, declared, used);
+        }
+        NodeKind::Reassignment { name, value } => {
+            used.push((name, node));
+            walk(value, declared, used);
+        }
+        NodeKind::AttributeAssignment { object, value, .. } => {
+            walk(object, declared, used);
+            walk(value, declared, used);
+        }
+        NodeKind::Function {
+            name,
+            parameters,
+            code,
+            ..
+        } => {
+            declared.insert(name);
+            declared.extend(parameters.iter().map(String::as_str));
+            walk(code, declared, used);
+        }
+        NodeKind::Return(value) | NodeKind::Not(value) | NodeKind::Negate(value) => {
+            walk(value, declared, used)
+        }
+        NodeKind::Call { callee, arguments } => {
+            walk(callee, declared, used);
+
+            for argument in arguments {
+                walk(argument, declared, used);
+            }
+        }
+        NodeKind::AttributeResolve { parent, value } => {
+            walk(parent, declared, used);
+            walk_attribute(value, declared, used);
+        }
+        NodeKind::If {
+            condition,
+            alternative,
+            block,
+        } => {
+            walk(condition, declared, used);
+            walk(block, declared, used);
+            walk(alternative, declared, used);
+        }
+        NodeKind::While {
+            condition, block, ..
+        } => {
+            walk(condition, declared, used);
+            walk(block, declared, used);
+        }
+        NodeKind::For {
+            variable,
+            iterable,
+            block,
+            ..
+        } => {
+            declared.insert(variable);
+            walk(iterable, declared, used);
+            walk(block, declared, used);
+        }
+        NodeKind::Array(nodes) | NodeKind::Program(nodes) | NodeKind::Block(nodes) => {
+            for node in nodes {
+                walk(node, declared, used);
+            }
+        }
+        _ => unreachable!("binary operations are handled above"),
+    }
+}
+
+/// Walks the part of an attribute chain after the first `.`, where identifiers
+/// name attributes rather than variables.
+fn walk_attribute<'a>(node: &'a Node, declared: &mut HashSet<&'a str>, used: &mut Vec<(&'a str, &'a Node)>) {
+    match &node.kind {
+        NodeKind::AttributeResolve { parent, value } => {
+            walk_attribute(parent, declared, used);
+            walk_attribute(value, declared, used);
+        }
+        NodeKind::Call { arguments, .. } => {
+            for argument in arguments {
+                walk(argument, declared, used);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,634 @@
+use std::rc::Rc;
+
+use crate::{
+    bytecode::{Capture, Chunk, Instruction, Prototype},
+    interpreter::{RuntimeError, RuntimeErrorKind, Value},
+    number::Operation,
+    parser::{Node, NodeKind, Part},
+    span::Span,
+};
+
+type CResult = Result<(), RuntimeError>;
+
+struct Local {
+    name: String,
+    depth: usize,
+}
+
+struct Loop {
+    label: Option<String>,
+    /// `for` loops keep an iterator alive that `break` has to finish.
+    iterates: bool,
+    start: usize,
+    breaks: Vec<usize>,
+}
+
+/// Compilation state of one function. Nested function declarations push a new one.
+struct FunctionState {
+    name: String,
+    chunk: Chunk,
+    locals: Vec<Local>,
+    depth: usize,
+    slots: usize,
+    /// Slots read or written by a nested function, which have to live in cells.
+    captured: Vec<bool>,
+    captures: Vec<Capture>,
+    loops: Vec<Loop>,
+}
+
+impl FunctionState {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            chunk: Chunk::default(),
+            locals: Vec::new(),
+            depth: 0,
+            slots: 0,
+            captured: Vec::new(),
+            captures: Vec::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|a| a.name == name)
+    }
+
+    fn capture(&mut self, capture: Capture) -> usize {
+        if let Some(index) = self.captures.iter().position(|a| *a == capture) {
+            return index;
+        }
+
+        self.captures.push(capture);
+        self.captures.len() - 1
+    }
+}
+
+/// Compiles a parsed program into the prototype of a function taking no arguments.
+/// The compiler recurses over the tree, and relies on the parser to keep it shallow.
+pub fn compile(program: &Node) -> Result<Rc<Prototype>, RuntimeError> {
+    let mut compiler = Compiler {
+        functions: vec![FunctionState::new("<script>")],
+    };
+
+    compiler.compile_statements(program)?;
+    compiler.emit(Instruction::Nil, program.span);
+    compiler.emit(Instruction::Return, program.span);
+
+    Ok(Rc::new(compiler.finish(0)))
+}
+
+/// Variables resolve to a local slot of the current function, then to a
+/// variable captured from an enclosing function, and finally to a global.
+/// Top-level `let` and `func` outside of any block define globals.
+struct Compiler {
+    functions: Vec<FunctionState>,
+}
+
+impl Compiler {
+    fn state(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().expect("compiler has no function")
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Span) -> usize {
+        let chunk = &mut self.state().chunk;
+        chunk.code.push(instruction);
+        chunk.spans.push(span);
+
+        chunk.code.len() - 1
+    }
+
+    fn position(&mut self) -> usize {
+        self.state().chunk.code.len()
+    }
+
+    /// Points the jump at `index` to the next instruction to be emitted.
+    fn patch(&mut self, index: usize) {
+        let target = self.position();
+
+        match &mut self.state().chunk.code[index] {
+            Instruction::Jump(to)
+            | Instruction::JumpIfFalse(to)
+            | Instruction::JumpIfTrue(to)
+            | Instruction::IterNext(to) => *to = target,
+            other => unreachable!("cannot patch {other:?}"),
+        }
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        let constants = &mut self.state().chunk.constants;
+        constants.push(value);
+
+        constants.len() - 1
+    }
+
+    fn name(&mut self, name: &str) -> usize {
+        let names = &mut self.state().chunk.names;
+
+        if let Some(index) = names.iter().position(|a| a == name) {
+            return index;
+        }
+
+        names.push(name.to_string());
+        names.len() - 1
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.functions.len() == 1 && self.functions[0].depth == 0
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        let state = self.state();
+        state.depth -= 1;
+
+        while state.locals.last().is_some_and(|a| a.depth > state.depth) {
+            state.locals.pop();
+        }
+    }
+
+    fn declare_local(&mut self, name: &str) -> usize {
+        let state = self.state();
+
+        state.locals.push(Local {
+            name: name.to_string(),
+            depth: state.depth,
+        });
+
+        let slot = state.locals.len() - 1;
+
+        state.slots = state.slots.max(slot + 1);
+        state.captured.resize(state.slots, false);
+
+        slot
+    }
+
+    /// Finds `name` in the functions enclosing `function`, capturing it into every closure in between.
+    fn resolve_upvalue(&mut self, function: usize, name: &str) -> Option<usize> {
+        if function == 0 {
+            return None;
+        }
+
+        if let Some(slot) = self.functions[function - 1].resolve_local(name) {
+            self.functions[function - 1].captured[slot] = true;
+
+            return Some(self.functions[function].capture(Capture::Local(slot)));
+        }
+
+        let upvalue = self.resolve_upvalue(function - 1, name)?;
+
+        Some(self.functions[function].capture(Capture::Upvalue(upvalue)))
+    }
+
+    fn get_variable(&mut self, name: &str, span: Span) {
+        let current = self.functions.len() - 1;
+
+        let instruction = if let Some(slot) = self.functions[current].resolve_local(name) {
+            Instruction::GetLocal(slot)
+        } else if let Some(upvalue) = self.resolve_upvalue(current, name) {
+            Instruction::GetUpvalue(upvalue)
+        } else {
+            Instruction::GetGlobal(self.name(name))
+        };
+
+        self.emit(instruction, span);
+    }
+
+    fn set_variable(&mut self, name: &str, span: Span) {
+        let current = self.functions.len() - 1;
+
+        let instruction = if let Some(slot) = self.functions[current].resolve_local(name) {
+            Instruction::SetLocal(slot)
+        } else if let Some(upvalue) = self.resolve_upvalue(current, name) {
+            Instruction::SetUpvalue(upvalue)
+        } else {
+            Instruction::SetGlobal(self.name(name))
+        };
+
+        self.emit(instruction, span);
+    }
+
+    /// Defines a variable from the value on top of the stack.
+    fn define_variable(&mut self, name: &str, span: Span) {
+        let instruction = if self.is_global_scope() {
+            Instruction::DefineGlobal(self.name(name))
+        } else {
+            Instruction::DefineLocal(self.declare_local(name))
+        };
+
+        self.emit(instruction, span);
+    }
+
+    /// Pops the innermost function and turns its captured slots into cells.
+    fn finish(&mut self, arity: usize) -> Prototype {
+        let mut state = self.functions.pop().expect("compiler has no function");
+
+        for instruction in &mut state.chunk.code {
+            *instruction = match *instruction {
+                Instruction::GetLocal(slot) if state.captured[slot] => Instruction::GetCell(slot),
+                Instruction::SetLocal(slot) if state.captured[slot] => Instruction::SetCell(slot),
+                Instruction::DefineLocal(slot) if state.captured[slot] => {
+                    Instruction::DefineCell(slot)
+                }
+                other => other,
+            };
+        }
+
+        Prototype {
+            name: state.name,
+            arity,
+            slots: state.slots,
+            captured_parameters: state.captured[..arity].to_vec(),
+            captures: state.captures,
+            chunk: state.chunk,
+        }
+    }
+
+    /// `Parser::parse` collapses a single statement into the node itself,
+    /// so a body is either a `Program`/`Block` or one bare statement.
+    fn compile_statements(&mut self, node: &Node) -> CResult {
+        match &node.kind {
+            NodeKind::Program(nodes) | NodeKind::Block(nodes) => {
+                for node in nodes {
+                    self.compile_statement(node)?;
+                }
+
+                Ok(())
+            }
+            _ => self.compile_statement(node),
+        }
+    }
+
+    fn compile_block(&mut self, node: &Node) -> CResult {
+        self.begin_scope();
+        self.compile_statements(node)?;
+        self.end_scope();
+
+        Ok(())
+    }
+
+    fn compile_statement(&mut self, node: &Node) -> CResult {
+        let span = node.span;
+
+        match &node.kind {
+            NodeKind::Assignment { name, value, .. } => {
+                self.compile_expression(value)?;
+                self.define_variable(name, span);
+            }
+            NodeKind::Reassignment { name, value } => {
+                self.compile_expression(value)?;
+                self.set_variable(name, span);
+            }
+            NodeKind::AttributeAssignment {
+                object,
+                attribute,
+                value,
+            } => {
+                self.compile_expression(object)?;
+                self.compile_expression(value)?;
+
+                let index = self.name(attribute);
+                self.emit(Instruction::SetAttribute(index), span);
+            }
+            NodeKind::Function {
+                name,
+                parameters,
+                code,
+                ..
+            } => {
+                if self.is_global_scope() {
+                    self.compile_function(name, parameters, code, span)?;
+                    self.define_variable(name, span);
+                } else {
+                    // Declared before the body is compiled so the function can call itself.
+                    let slot = self.declare_local(name);
+
+                    self.emit(Instruction::Nil, span);
+                    self.emit(Instruction::DefineLocal(slot), span);
+                    self.compile_function(name, parameters, code, span)?;
+                    self.emit(Instruction::SetLocal(slot), span);
+                }
+            }
+            NodeKind::Return(value) => {
+                self.compile_expression(value)?;
+                self.emit(Instruction::Return, span);
+            }
+            NodeKind::If {
+                condition,
+                alternative,
+                block,
+            } => {
+                self.compile_expression(condition)?;
+
+                let otherwise = self.emit(Instruction::JumpIfFalse(0), span);
+                self.compile_block(block)?;
+
+                let end = self.emit(Instruction::Jump(0), span);
+                self.patch(otherwise);
+                self.compile_block(alternative)?;
+                self.patch(end);
+            }
+            NodeKind::While {
+                label,
+                condition,
+                block,
+            } => {
+                let start = self.position();
+
+                self.compile_expression(condition)?;
+
+                let exit = self.emit(Instruction::JumpIfFalse(0), span);
+                self.compile_loop_body(label, false, start, block, span, exit)?;
+            }
+            NodeKind::For {
+                label,
+                variable,
+                iterable,
+                block,
+            } => {
+                self.compile_expression(iterable)?;
+                self.emit(Instruction::IterStart, span);
+
+                let start = self.position();
+                let exit = self.emit(Instruction::IterNext(0), span);
+
+                self.begin_scope();
+
+                let slot = self.declare_local(variable);
+                self.emit(Instruction::DefineLocal(slot), span);
+                self.compile_loop_body(label, true, start, block, span, exit)?;
+
+                self.end_scope();
+            }
+            NodeKind::Break(label) => {
+                let index = self.find_loop(label, span)?;
+
+                // Finish the iterations of every loop being left, including the target.
+                let iterations = self.state().loops[index..].iter().filter(|a| a.iterates).count();
+
+                for _ in 0..iterations {
+                    self.emit(Instruction::IterEnd, span);
+                }
+
+                let jump = self.emit(Instruction::Jump(0), span);
+                self.state().loops[index].breaks.push(jump);
+            }
+            NodeKind::Continue(label) => {
+                let index = self.find_loop(label, span)?;
+
+                let iterations = self.state().loops[index + 1..].iter().filter(|a| a.iterates).count();
+
+                for _ in 0..iterations {
+                    self.emit(Instruction::IterEnd, span);
+                }
+
+                let start = self.state().loops[index].start;
+                self.emit(Instruction::Jump(start), span);
+            }
+            NodeKind::Program(_) => self.compile_statements(node)?,
+            NodeKind::Block(_) => self.compile_block(node)?,
+            _ => {
+                self.compile_expression(node)?;
+                self.emit(Instruction::Pop, span);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a loop body that jumps back to `start`, with `exit` being the
+    /// jump taken once the loop ends normally.
+    fn compile_loop_body(
+        &mut self,
+        label: &Option<String>,
+        iterates: bool,
+        start: usize,
+        block: &Node,
+        span: Span,
+        exit: usize,
+    ) -> CResult {
+        self.state().loops.push(Loop {
+            label: label.clone(),
+            iterates,
+            start,
+            breaks: Vec::new(),
+        });
+
+        self.compile_block(block)?;
+        self.emit(Instruction::Jump(start), span);
+
+        let finished = self.state().loops.pop().expect("loop was pushed above");
+
+        self.patch(exit);
+
+        for jump in finished.breaks {
+            self.patch(jump);
+        }
+
+        Ok(())
+    }
+
+    fn find_loop(&mut self, label: &Option<String>, span: Span) -> Result<usize, RuntimeError> {
+        let loops = &self.state().loops;
+
+        let index = match label {
+            None => loops.len().checked_sub(1),
+            Some(_) => loops.iter().rposition(|a| a.label == *label),
+        };
+
+        index.ok_or_else(|| RuntimeError::new(RuntimeErrorKind::JumpOutsideLoop).or_span(span))
+    }
+
+    fn compile_function(&mut self, name: &str, parameters: &[String], code: &Node, span: Span) -> CResult {
+        self.functions.push(FunctionState::new(name));
+        self.begin_scope();
+
+        for parameter in parameters {
+            self.declare_local(parameter);
+        }
+
+        self.compile_statements(code)?;
+        self.emit(Instruction::Nil, span);
+        self.emit(Instruction::Return, span);
+
+        let prototype = self.finish(parameters.len());
+
+        let functions = &mut self.state().chunk.functions;
+        functions.push(Rc::new(prototype));
+
+        let index = functions.len() - 1;
+        self.emit(Instruction::Closure(index), span);
+
+        Ok(())
+    }
+
+    fn compile_binary(&mut self, lhs: &Node, rhs: &Node, instruction: Instruction, span: Span) -> CResult {
+        self.compile_expression(lhs)?;
+        self.compile_expression(rhs)?;
+        self.emit(instruction, span);
+
+        Ok(())
+    }
+
+    /// `||` and `&&` skip their right-hand side once the left one decides the result.
+    fn compile_logical(&mut self, lhs: &Node, rhs: &Node, or: bool, span: Span) -> CResult {
+        self.compile_expression(lhs)?;
+
+        let short_circuit = match or {
+            true => self.emit(Instruction::JumpIfTrue(0), span),
+            false => self.emit(Instruction::JumpIfFalse(0), span),
+        };
+
+        self.compile_expression(rhs)?;
+        self.emit(Instruction::ToBool, span);
+
+        let end = self.emit(Instruction::Jump(0), span);
+        self.patch(short_circuit);
+        self.emit(if or { Instruction::True } else { Instruction::False }, span);
+        self.patch(end);
+
+        Ok(())
+    }
+
+    fn compile_expression(&mut self, node: &Node) -> CResult {
+        let span = node.span;
+
+        match &node.kind {
+            NodeKind::Ident(name) => self.get_variable(name, span),
+            NodeKind::Number(nr) => {
+                let index = self.constant(Value::Number(nr.clone()));
+                self.emit(Instruction::Constant(index), span);
+            }
+            NodeKind::String(st) => {
+                let index = self.constant(Value::String(st.clone()));
+                self.emit(Instruction::Constant(index), span);
+            }
+            NodeKind::Interpolated(parts) => {
+                for part in parts {
+                    match part {
+                        Part::Literal(literal) => {
+                            let index = self.constant(Value::String(literal.clone()));
+                            self.emit(Instruction::Constant(index), span);
+                        }
+                        Part::Expression(expression) => self.compile_expression(expression)?,
+                    }
+                }
+
+                self.emit(Instruction::Concat(parts.len()), span);
+            }
+            NodeKind::Add(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Arithmetic(Operation::Add), span)?
+            }
+            NodeKind::Subtract(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Arithmetic(Operation::Subtract), span)?
+            }
+            NodeKind::Multiply(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Arithmetic(Operation::Multiply), span)?
+            }
+            NodeKind::Divide(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Arithmetic(Operation::Divide), span)?
+            }
+            NodeKind::Modulo(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Arithmetic(Operation::Modulo), span)?
+            }
+            NodeKind::BinOr(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Arithmetic(Operation::BinOr), span)?
+            }
+            NodeKind::BinAnd(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Arithmetic(Operation::BinAnd), span)?
+            }
+            NodeKind::Not(value) => {
+                self.compile_expression(value)?;
+                self.emit(Instruction::Not, span);
+            }
+            NodeKind::Negate(value) => {
+                self.compile_expression(value)?;
+                self.emit(Instruction::Negate, span);
+            }
+            NodeKind::Or(lhs, rhs) => self.compile_logical(lhs, rhs, true, span)?,
+            NodeKind::And(lhs, rhs) => self.compile_logical(lhs, rhs, false, span)?,
+            NodeKind::Equals(lhs, rhs) => self.compile_binary(lhs, rhs, Instruction::Equal, span)?,
+            NodeKind::NotEquals(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::NotEqual, span)?
+            }
+            NodeKind::Less(lhs, rhs) => self.compile_binary(lhs, rhs, Instruction::Less, span)?,
+            NodeKind::Greater(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::Greater, span)?
+            }
+            NodeKind::LessEquals(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::LessEqual, span)?
+            }
+            NodeKind::GreaterEquals(lhs, rhs) => {
+                self.compile_binary(lhs, rhs, Instruction::GreaterEqual, span)?
+            }
+            NodeKind::Range(start, end) => {
+                self.compile_binary(start, end, Instruction::Range, span)?
+            }
+            NodeKind::Call { callee, arguments } => {
+                self.compile_expression(callee)?;
+                self.compile_call(arguments, span)?;
+            }
+            NodeKind::AttributeResolve { parent, value } => {
+                self.compile_expression(parent)?;
+                self.compile_attribute(value, span)?;
+            }
+            NodeKind::Array(elements) => {
+                for element in elements {
+                    self.compile_expression(element)?;
+                }
+
+                self.emit(Instruction::Array(elements.len()), span);
+            }
+            // Statements used as values evaluate to nil.
+            _ => {
+                self.compile_statement(node)?;
+                self.emit(Instruction::Nil, span);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_call(&mut self, arguments: &[Node], span: Span) -> CResult {
+        for argument in arguments {
+            self.compile_expression(argument)?;
+        }
+
+        self.emit(Instruction::Call(arguments.len()), span);
+
+        Ok(())
+    }
+
+    /// The parser nests attribute chains to the right: `a.b.c()` is
+    /// `AttributeResolve { a, AttributeResolve { b, Call { c } } }`.
+    ///
+    /// Every instruction of the chain gets the `span` of the whole chain, which
+    /// is where the interpreter reports errors from attributes and method calls.
+    fn compile_attribute(&mut self, attribute: &Node, span: Span) -> CResult {
+        match &attribute.kind {
+            NodeKind::Ident(name) => {
+                let index = self.name(name);
+                self.emit(Instruction::GetAttribute(index), span);
+            }
+            NodeKind::Call { callee, arguments } => {
+                let Some(name) = callee.ident() else {
+                    return Err(RuntimeError::new(RuntimeErrorKind::InvalidAttribute).or_span(span));
+                };
+
+                let index = self.name(name);
+                self.emit(Instruction::GetAttribute(index), span);
+                self.compile_call(arguments, span)?;
+            }
+            NodeKind::AttributeResolve { parent, value } => {
+                self.compile_attribute(parent, span)?;
+                self.compile_attribute(value, span)?;
+            }
+            _ => return Err(RuntimeError::new(RuntimeErrorKind::InvalidAttribute).or_span(span)),
+        }
+
+        Ok(())
+    }
+}
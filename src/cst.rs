@@ -0,0 +1,465 @@
+use std::{fmt, ops::Range, rc::Rc};
+
+use crate::{
+    lexer::{Lexem, LexemKind, Trivia, TriviaKind},
+    parser::{Node, NodeKind},
+    span::Span,
+};
+
+/// What a node or token of the concrete syntax tree is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Whitespace,
+    Newline,
+    Comment,
+    DocComment,
+    Ident,
+    Number,
+    /// A string literal, interpolated or not.
+    String,
+    Punctuation,
+
+    SourceFile,
+    Program,
+    Name,
+    Literal,
+    Assignment,
+    Reassignment,
+    AttributeAssignment,
+    Function,
+    Return,
+    Binary,
+    Unary,
+    Call,
+    Attribute,
+    If,
+    While,
+    For,
+    Jump,
+    Array,
+    Block,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace | SyntaxKind::Newline | SyntaxKind::Comment | SyntaxKind::DocComment
+        )
+    }
+}
+
+/// A token of the green tree: its kind and its exact text.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: impl Into<String>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// A node of the green tree. It knows its width but not its position, so it can be
+/// shared between trees, and an edit only rebuilds the nodes on the path to the root.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            width: children.iter().map(GreenElement::width).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// Length of the text in bytes.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+/// The exact source text the node was built from.
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => write!(f, "{node}")?,
+                GreenElement::Token(token) => write!(f, "{}", token.text)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn width(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.width,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+/// A node of the red tree: a green node seen from a particular position in a file,
+/// with a link to its parent. Red nodes are created on demand while walking down
+/// from the root and are cheap to clone.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<RedNode>);
+
+struct RedNode {
+    green: Rc<GreenNode>,
+    offset: usize,
+    /// Position among the parent's children.
+    index: usize,
+    parent: Option<SyntaxNode>,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(RedNode {
+            green,
+            offset: 0,
+            index: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    /// Byte offsets of the node's text in the file.
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.width
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+
+        self.0.green.children.iter().enumerate().map(move |(index, child)| {
+            let start = offset;
+            offset += child.width();
+
+            match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(RedNode {
+                    green: Rc::clone(green),
+                    offset: start,
+                    index,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: Rc::clone(green),
+                    offset: start,
+                    parent: self.clone(),
+                }),
+            }
+        })
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token below this node, trivia included, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+
+    /// The green tree of the whole file with this node replaced by `replacement`.
+    /// Only the ancestors of this node are rebuilt; everything else is shared.
+    pub fn replace_with(&self, replacement: Rc<GreenNode>) -> Rc<GreenNode> {
+        let Some(parent) = &self.0.parent else {
+            return replacement;
+        };
+
+        let mut children = parent.0.green.children.clone();
+        children[self.0.index] = GreenElement::Node(replacement);
+
+        parent.replace_with(Rc::new(GreenNode::new(parent.kind(), children)))
+    }
+
+    fn write_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(f, "{}{:?}@{:?}", "  ".repeat(depth), self.kind(), self.range())?;
+
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => node.write_tree(f, depth + 1)?,
+                SyntaxElement::Token(token) => writeln!(
+                    f,
+                    "{}{:?}@{:?} {:?}",
+                    "  ".repeat(depth + 1),
+                    token.kind(),
+                    token.range(),
+                    token.text()
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+/// Prints the tree one element per line, indented by depth, with byte ranges.
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+}
+
+/// Builds the concrete syntax tree of `source` from the syntax tree the parser made of
+/// it and the lexems it was parsed from, which must carry their trivia. `trailing` is
+/// the trivia after the last lexem.
+///
+/// Every parsed node becomes a tree node over the lexems it spans. Trivia goes to the
+/// innermost node around it, so nodes start and end with a lexem.
+pub fn build(source: &str, ast: &Node, lexems: &[Lexem], trailing: &[Trivia]) -> SyntaxNode {
+    let mut pieces = Vec::new();
+
+    for lexem in lexems {
+        pieces.extend(lexem.trivia().iter().map(|a| (trivia_kind(a.kind), a.span)));
+        pieces.push((token_kind(lexem.token()), lexem.span()));
+    }
+
+    pieces.extend(trailing.iter().map(|a| (trivia_kind(a.kind), a.span)));
+
+    let mut builder = Builder {
+        source,
+        pieces,
+        position: 0,
+    };
+
+    let statements = match &ast.kind {
+        NodeKind::Program(statements) => statements.iter().collect(),
+        _ => vec![ast],
+    };
+
+    let mut children = Vec::new();
+    builder.nodes(statements, &mut children);
+    builder.tokens_until(source.len(), &mut children);
+
+    SyntaxNode::new_root(Rc::new(GreenNode::new(SyntaxKind::SourceFile, children)))
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    /// Lexems and trivia in source order.
+    pieces: Vec<(SyntaxKind, Span)>,
+    position: usize,
+}
+
+impl Builder<'_> {
+    /// Adds the pieces that end at or before `offset` as tokens.
+    fn tokens_until(&mut self, offset: usize, out: &mut Vec<GreenElement>) {
+        while let Some((kind, span)) = self.pieces.get(self.position)
+            && span.end.offset <= offset
+        {
+            let text = &self.source[span.start.offset..span.end.offset];
+            out.push(GreenElement::Token(Rc::new(GreenToken::new(*kind, text))));
+
+            self.position += 1;
+        }
+    }
+
+    fn nodes(&mut self, mut nodes: Vec<&Node>, out: &mut Vec<GreenElement>) {
+        nodes.sort_by_key(|a| a.span.start.offset);
+
+        for node in nodes {
+            self.tokens_until(node.span.start.offset, out);
+
+            // Nodes that do not start at a lexem of their own, like the empty `else` the
+            // parser adds to an `if`, have no text to cover.
+            let starts_here = self
+                .pieces
+                .get(self.position)
+                .is_some_and(|(_, span)| span.start.offset == node.span.start.offset);
+
+            if starts_here {
+                self.node(node, out);
+            }
+        }
+    }
+
+    fn node(&mut self, node: &Node, out: &mut Vec<GreenElement>) {
+        let mut children = Vec::new();
+
+        self.nodes(nested(node), &mut children);
+        self.tokens_until(node.span.end.offset, &mut children);
+
+        out.push(GreenElement::Node(Rc::new(GreenNode::new(syntax_kind(node), children))));
+    }
+}
+
+/// The nodes directly below `node`. The expressions in an interpolated string are
+/// part of its lexem and stay out of the tree.
+fn nested(node: &Node) -> Vec<&Node> {
+    if let Some((_, lhs, rhs)) = node.binary() {
+        return vec![lhs, rhs];
+    }
+
+    match &node.kind {
+        NodeKind::Ident(_)
+        | NodeKind::Number(_)
+        | NodeKind::String(_)
+        | NodeKind::Interpolated(_)
+        | NodeKind::Break(_)
+        | NodeKind::Continue(_) => Vec::new(),
+        NodeKind::Assignment { value, .. }
+        | NodeKind::Reassignment { value, .. }
+        | NodeKind::Return(value)
+        | NodeKind::Not(value)
+        | NodeKind::Negate(value) => vec![value],
+        NodeKind::AttributeAssignment { object, value, .. } => vec![object, value],
+        NodeKind::Function { code, .. } => vec![code],
+        NodeKind::Call { callee, arguments } => [callee.as_ref()].into_iter().chain(arguments).collect(),
+        NodeKind::AttributeResolve { parent, value } => vec![parent, value],
+        NodeKind::If {
+            condition,
+            alternative,
+            block,
+        } => vec![condition, block, alternative],
+        NodeKind::While {
+            condition, block, ..
+        } => vec![condition, block],
+        NodeKind::For {
+            iterable, block, ..
+        } => vec![iterable, block],
+        NodeKind::Array(nodes) | NodeKind::Program(nodes) | NodeKind::Block(nodes) => nodes.iter().collect(),
+        _ => unreachable!("binary operations are handled above"),
+    }
+}
+
+fn syntax_kind(node: &Node) -> SyntaxKind {
+    if node.binary().is_some() {
+        return SyntaxKind::Binary;
+    }
+
+    match &node.kind {
+        NodeKind::Ident(_) => SyntaxKind::Name,
+        NodeKind::Number(_) | NodeKind::String(_) | NodeKind::Interpolated(_) => SyntaxKind::Literal,
+        NodeKind::Assignment { .. } => SyntaxKind::Assignment,
+        NodeKind::Reassignment { .. } => SyntaxKind::Reassignment,
+        NodeKind::AttributeAssignment { .. } => SyntaxKind::AttributeAssignment,
+        NodeKind::Function { .. } => SyntaxKind::Function,
+        NodeKind::Return(_) => SyntaxKind::Return,
+        NodeKind::Not(_) | NodeKind::Negate(_) => SyntaxKind::Unary,
+        NodeKind::Call { .. } => SyntaxKind::Call,
+        NodeKind::AttributeResolve { .. } => SyntaxKind::Attribute,
+        NodeKind::If { .. } => SyntaxKind::If,
+        NodeKind::While { .. } => SyntaxKind::While,
+        NodeKind::For { .. } => SyntaxKind::For,
+        NodeKind::Break(_) | NodeKind::Continue(_) => SyntaxKind::Jump,
+        NodeKind::Array(_) => SyntaxKind::Array,
+        NodeKind::Program(_) => SyntaxKind::Program,
+        NodeKind::Block(_) => SyntaxKind::Block,
+        _ => unreachable!("binary operations are handled above"),
+    }
+}
+
+fn token_kind(kind: &LexemKind) -> SyntaxKind {
+    match kind {
+        LexemKind::Ident(_) => SyntaxKind::Ident,
+        LexemKind::Number(_) => SyntaxKind::Number,
+        LexemKind::StringLiteral(_) | LexemKind::Interpolated(_) => SyntaxKind::String,
+        _ => SyntaxKind::Punctuation,
+    }
+}
+
+fn trivia_kind(kind: TriviaKind) -> SyntaxKind {
+    match kind {
+        TriviaKind::Whitespace => SyntaxKind::Whitespace,
+        TriviaKind::Newline => SyntaxKind::Newline,
+        TriviaKind::Comment => SyntaxKind::Comment,
+        TriviaKind::DocComment => SyntaxKind::DocComment,
+    }
+}
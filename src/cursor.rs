@@ -27,22 +27,22 @@ impl<T> VecCursor<T> {
         ret
     }
 
-    pub fn prev(&self) -> Option<&T> {
-        let pos = *self.position.borrow();
-
-        if pos == 0 {
-            return None;
-        }
+    pub fn current(&self) -> Option<&T> {
+        self.inner.get(*self.position.borrow())
+    }
 
-        let ret = self.inner.get(pos);
-        
-        *self.position.borrow_mut() -= 1;
+    /// Looks `offset` elements ahead of the current one without moving.
+    pub fn peek(&self, offset: usize) -> Option<&T> {
+        self.inner.get(*self.position.borrow() + offset)
+    }
 
-        ret
+    /// The element before the current one, i.e. the last one returned by `next`.
+    pub fn previous(&self) -> Option<&T> {
+        self.inner.get(self.position().checked_sub(1)?)
     }
 
-    pub fn current(&self) -> Option<&T> {
-        self.inner.get(*self.position.borrow())
+    pub fn last(&self) -> Option<&T> {
+        self.inner.last()
     }
 
     pub fn position(&self) -> usize {
@@ -0,0 +1,343 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    interpreter::{RuntimeError, RuntimeErrorKind},
+    lexer::LexError,
+    parser::{Expected, ParseError},
+    span::Span,
+    tokenizer::{TokenizerError, TokenizerErrorKind},
+};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    message: String,
+    span: Option<Span>,
+    label: Option<String>,
+    help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            span: None,
+            label: None,
+            help: Vec::new(),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Text printed next to the underline.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn help(&self) -> &[String] {
+        &self.help
+    }
+}
+
+/// One line, without the source: `3:7: message`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "{}:{}: ", span.line(), span.column())?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+/// The problems that stopped a piece of code from being parsed or run.
+#[derive(Debug, Clone)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Renders every diagnostic with `renderer`, separated by blank lines.
+    pub fn render(&self, renderer: &Renderer) -> String {
+        self.iter().map(|a| renderer.render(a) + "\n").collect()
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, diagnostic) in self.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{diagnostic}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self(vec![diagnostic])
+    }
+}
+
+impl FromIterator<Diagnostic> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = Diagnostic>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl From<&TokenizerError> for Diagnostic {
+    fn from(e: &TokenizerError) -> Self {
+        let diagnostic = Diagnostic::error(e.to_string()).with_span(e.span());
+
+        match e.kind() {
+            TokenizerErrorKind::UnterminatedString => {
+                diagnostic.with_label("string is never closed").with_help("add a closing `\"`")
+            }
+            TokenizerErrorKind::InvalidEscapeSequence => diagnostic
+                .with_label("unknown escape")
+                .with_help("supported escapes are `\\\"`, `\\'`, `\\\\`, `\\n`, `\\t`, `\\r`, `\\0`, `\\x7F` and `\\u{1F600}`")
+                .with_help("use a raw string like `r\"C:\\path\"` to avoid escaping"),
+            TokenizerErrorKind::InvalidHexEscape => diagnostic
+                .with_label("invalid hex escape")
+                .with_help("`\\x` takes exactly two hex digits, from `\\x00` to `\\x7F`"),
+            TokenizerErrorKind::InvalidUnicodeEscape => diagnostic
+                .with_label("invalid unicode escape")
+                .with_help("`\\u{…}` takes one to six hex digits naming a Unicode scalar value"),
+            TokenizerErrorKind::InvalidNumber => diagnostic
+                .with_label("invalid number literal")
+                .with_help("numbers look like `42`, `3.14`, `1e-9`, `0xFF`, `0b1010`, `0o17` or `1_000`"),
+            TokenizerErrorKind::NumberOutOfRange => diagnostic
+                .with_label("too large")
+                .with_help("float literals must be at most about `1.8e308`"),
+            TokenizerErrorKind::InterpolationTooDeep => diagnostic
+                .with_label("nested too deeply")
+                .with_help("build the inner strings first and bind them with `let`"),
+            TokenizerErrorKind::UnmatchedBrace => diagnostic
+                .with_label("unmatched brace")
+                .with_help("use `}}` to insert a literal `}`"),
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(e: &LexError) -> Self {
+        let diagnostic = || Diagnostic::error(e.to_string()).with_span(e.span());
+
+        match e {
+            LexError::Tokenizer(e) => Diagnostic::from(e),
+            LexError::UnknownCharacter(..) => diagnostic().with_label("not valid here"),
+            LexError::InvalidNumber(_) => diagnostic().with_label("invalid number literal"),
+            LexError::UnterminatedComment(_) => diagnostic()
+                .with_label("comment is never closed")
+                .with_help("add a closing `*/`"),
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(e: &ParseError) -> Self {
+        let found = match e.found() {
+            Some(kind) => format!("`{kind}`"),
+            None => "end of input".to_string(),
+        };
+
+        let diagnostic = Diagnostic::error(e.message())
+            .with_span(e.span())
+            .with_label(format!("expected {}, found {found}", e.expected()));
+
+        match e.expected() {
+            Expected::Shallower => diagnostic.with_help("split it up with `let` bindings or functions"),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(e: &RuntimeError) -> Self {
+        let mut diagnostic = Diagnostic::error(e.to_string());
+
+        if let RuntimeErrorKind::Overflow(_) = e.kind() {
+            diagnostic = diagnostic.with_help("use `bigint.from(...)` for integers that do not fit in 64 bits");
+        }
+
+        match e.span() {
+            Some(span) => diagnostic.with_span(span),
+            None => diagnostic,
+        }
+    }
+}
+
+/// Renders diagnostics against the source they were produced from.
+pub struct Renderer<'a> {
+    file_name: &'a str,
+    source: &'a str,
+    colors: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(file_name: &'a str, source: &'a str) -> Self {
+        Self {
+            file_name,
+            source,
+            colors: false,
+        }
+    }
+
+    pub fn colored(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.colors {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "{}{}",
+            self.paint(RED, "error"),
+            self.paint(BOLD, &format!(": {}", diagnostic.message)),
+        );
+
+        let Some(span) = diagnostic.span else {
+            let _ = writeln!(out, " {} {}", self.paint(BLUE, "-->"), self.file_name);
+            self.render_help(&mut out, diagnostic, 1);
+
+            return out;
+        };
+
+        let line_number = span.line().to_string();
+        let width = line_number.len();
+        let gutter = self.paint(BLUE, &format!("{:width$} |", ""));
+
+        let _ = writeln!(
+            out,
+            "{:width$}{} {}:{}:{}",
+            "",
+            self.paint(BLUE, "-->"),
+            self.file_name,
+            span.line(),
+            span.column(),
+        );
+
+        // Compiled programs are rendered without their source.
+        let Some(source_line) = self.source.lines().nth(span.line().saturating_sub(1)) else {
+            self.render_help(&mut out, diagnostic, width);
+
+            return out;
+        };
+
+        let _ = writeln!(out, "{gutter}");
+
+        let _ = writeln!(
+            out,
+            "{} {}",
+            self.paint(BLUE, &format!("{line_number} |")),
+            source_line
+        );
+
+        // Keep tabs in the padding so the underline lines up with the source line.
+        let padding: String = source_line
+            .chars()
+            .take(span.column().saturating_sub(1))
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        // Only the part of the span on its first line is underlined.
+        let length = self
+            .source
+            .get(span.start.offset..span.end.offset)
+            .map(|a| a.chars().take_while(|ch| *ch != '\n').count())
+            .unwrap_or(0)
+            .max(1);
+
+        let underline = format!("^{}", "~".repeat(length - 1));
+        let mut marker = self.paint(RED, &underline);
+
+        if let Some(label) = &diagnostic.label {
+            marker += &format!(" {}", self.paint(RED, label));
+        }
+
+        let _ = writeln!(out, "{gutter} {padding}{marker}");
+
+        self.render_help(&mut out, diagnostic, width);
+
+        out
+    }
+
+    fn render_help(&self, out: &mut String, diagnostic: &Diagnostic, width: usize) {
+        if diagnostic.help.is_empty() {
+            return;
+        }
+
+        let _ = writeln!(out, "{}", self.paint(BLUE, &format!("{:width$} |", "")));
+
+        for help in &diagnostic.help {
+            let _ = writeln!(
+                out,
+                "{:width$} {} {}: {help}",
+                "",
+                self.paint(BLUE, "="),
+                self.paint(CYAN, "help"),
+            );
+        }
+    }
+}
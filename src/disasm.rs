@@ -0,0 +1,120 @@
+use std::fmt::Write;
+
+use crate::bytecode::{Capture, Instruction, Prototype};
+
+/// Renders a compiled program as text, one function after another,
+/// starting with the script itself.
+pub fn disassemble(program: &Prototype) -> String {
+    let mut out = String::new();
+    disassemble_function(&mut out, program);
+
+    out
+}
+
+fn disassemble_function(out: &mut String, prototype: &Prototype) {
+    let _ = writeln!(
+        out,
+        "== {} ({} parameter(s), {} slot(s)) ==",
+        prototype.name, prototype.arity, prototype.slots
+    );
+
+    if !prototype.captures.is_empty() {
+        let captures: Vec<_> = prototype
+            .captures
+            .iter()
+            .map(|capture| match capture {
+                Capture::Local(slot) => format!("local {slot}"),
+                Capture::Upvalue(index) => format!("upvalue {index}"),
+            })
+            .collect();
+
+        let _ = writeln!(out, "captures: {}", captures.join(", "));
+    }
+
+    let chunk = &prototype.chunk;
+    let mut previous_line = None;
+
+    for (index, instruction) in chunk.code.iter().enumerate() {
+        let line = chunk.spans[index].line();
+
+        // Like a listing, only print the line number when it changes.
+        let line_column = if previous_line == Some(line) {
+            "   |".to_string()
+        } else {
+            format!("{line:4}")
+        };
+
+        previous_line = Some(line);
+
+        let (name, operand, comment) = describe(prototype, *instruction);
+
+        let mut text = name.to_string();
+
+        if let Some(operand) = operand {
+            let _ = write!(text, " {operand}");
+        }
+
+        match comment {
+            Some(comment) => {
+                let _ = writeln!(out, "{index:04} {line_column}  {text:<20} ; {comment}");
+            }
+            None => {
+                let _ = writeln!(out, "{index:04} {line_column}  {text}");
+            }
+        }
+    }
+
+    for function in &chunk.functions {
+        let _ = writeln!(out);
+        disassemble_function(out, function);
+    }
+}
+
+/// Mnemonic, operand and a note resolving the operand, for one instruction.
+fn describe(prototype: &Prototype, instruction: Instruction) -> (&'static str, Option<usize>, Option<String>) {
+    let chunk = &prototype.chunk;
+    let name = |index: usize| Some(chunk.names[index].clone());
+
+    match instruction {
+        Instruction::Constant(index) => ("constant", Some(index), Some(format!("{:?}", chunk.constants[index]))),
+        Instruction::Nil => ("nil", None, None),
+        Instruction::True => ("true", None, None),
+        Instruction::False => ("false", None, None),
+        Instruction::Pop => ("pop", None, None),
+        Instruction::GetLocal(slot) => ("get_local", Some(slot), None),
+        Instruction::SetLocal(slot) => ("set_local", Some(slot), None),
+        Instruction::DefineLocal(slot) => ("define_local", Some(slot), None),
+        Instruction::GetCell(slot) => ("get_cell", Some(slot), None),
+        Instruction::SetCell(slot) => ("set_cell", Some(slot), None),
+        Instruction::DefineCell(slot) => ("define_cell", Some(slot), None),
+        Instruction::GetUpvalue(index) => ("get_upvalue", Some(index), None),
+        Instruction::SetUpvalue(index) => ("set_upvalue", Some(index), None),
+        Instruction::GetGlobal(index) => ("get_global", Some(index), name(index)),
+        Instruction::SetGlobal(index) => ("set_global", Some(index), name(index)),
+        Instruction::DefineGlobal(index) => ("define_global", Some(index), name(index)),
+        Instruction::GetAttribute(index) => ("get_attribute", Some(index), name(index)),
+        Instruction::SetAttribute(index) => ("set_attribute", Some(index), name(index)),
+        Instruction::Arithmetic(operation) => ("arithmetic", None, Some(operation.symbol().to_string())),
+        Instruction::Negate => ("negate", None, None),
+        Instruction::Not => ("not", None, None),
+        Instruction::ToBool => ("to_bool", None, None),
+        Instruction::Equal => ("equal", None, None),
+        Instruction::NotEqual => ("not_equal", None, None),
+        Instruction::Less => ("less", None, None),
+        Instruction::Greater => ("greater", None, None),
+        Instruction::LessEqual => ("less_equal", None, None),
+        Instruction::GreaterEqual => ("greater_equal", None, None),
+        Instruction::Range => ("range", None, None),
+        Instruction::Array(count) => ("array", Some(count), None),
+        Instruction::Concat(count) => ("concat", Some(count), None),
+        Instruction::Jump(target) => ("jump", Some(target), None),
+        Instruction::JumpIfFalse(target) => ("jump_if_false", Some(target), None),
+        Instruction::JumpIfTrue(target) => ("jump_if_true", Some(target), None),
+        Instruction::IterStart => ("iter_start", None, None),
+        Instruction::IterNext(target) => ("iter_next", Some(target), None),
+        Instruction::IterEnd => ("iter_end", None, None),
+        Instruction::Closure(index) => ("closure", Some(index), Some(chunk.functions[index].name.clone())),
+        Instruction::Call(count) => ("call", Some(count), None),
+        Instruction::Return => ("return", None, None),
+    }
+}
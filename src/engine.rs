@@ -0,0 +1,83 @@
+use crate::{
+    Ast,
+    diagnostics::{Diagnostic, Diagnostics},
+    interpreter::{Interpreter, Value},
+    native::IntoNative,
+};
+
+/// Runs scripts one after another in a single global scope, so later scripts
+/// see the variables and functions of earlier ones.
+///
+/// ```
+/// let mut engine = maaray::Engine::new();
+///
+/// engine.eval("func square(x) { return x * x }").unwrap();
+/// let value = engine.eval("square(7)").unwrap();
+///
+/// assert_eq!(value.to_string(), "49");
+/// ```
+pub struct Engine {
+    interpreter: Interpreter,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    /// Parses and runs `code`, returning the value of its last statement.
+    pub fn eval(&mut self, code: &str) -> Result<Value, Diagnostics> {
+        let ast = crate::parse(code)?;
+
+        self.run(&ast)
+    }
+
+    /// Runs an already parsed program, returning the value of its last statement.
+    pub fn run(&mut self, ast: &Ast) -> Result<Value, Diagnostics> {
+        self.interpreter
+            .run(ast)
+            .map_err(|e| Diagnostic::from(&e).into())
+    }
+
+    /// Makes a Rust function callable from scripts as `name`. A dotted name like
+    /// `math.clamp` puts it in the module `math`, creating the module if needed.
+    ///
+    /// The function either takes the raw `&[Value]` arguments and returns
+    /// `Result<Value, RuntimeError>`, or takes up to five typed parameters, in which
+    /// case the argument count and types are checked before it is called.
+    ///
+    /// ```
+    /// use maaray::{Engine, IntoValue, RuntimeError, Value};
+    ///
+    /// let mut engine = Engine::new();
+    ///
+    /// engine.register_fn("math.clamp", |x: f64, low: f64, high: f64| x.clamp(low, high));
+    /// engine.register_fn("repeat", |text: String, count: i64| text.repeat(count as usize));
+    /// engine.register_fn("count", |arguments: &[Value]| -> Result<Value, RuntimeError> {
+    ///     Ok((arguments.len() as i64).into_value())
+    /// });
+    ///
+    /// assert_eq!(engine.eval("math.clamp(15, 0, 10)").unwrap().to_string(), "10.0");
+    /// assert_eq!(engine.eval(r#"repeat("ab", 3)"#).unwrap().to_string(), "ababab");
+    /// assert_eq!(engine.eval("count(1, 2, 3)").unwrap().to_string(), "3");
+    ///
+    /// let error = engine.eval(r#"repeat(3, "ab")"#).unwrap_err();
+    /// assert_eq!(error.to_string(), "1:1: argument 1 of `repeat` must be `string`, found `int`");
+    /// ```
+    pub fn register_fn<Args>(&mut self, name: &str, function: impl IntoNative<Args>) {
+        self.interpreter.register_fn(name, function);
+    }
+
+    /// Defines a global variable, replacing any existing one of that name.
+    pub fn define(&mut self, name: impl Into<String>, value: Value) {
+        self.interpreter.define_global(name, value);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{
+    parser::{Node, NodeKind},
+    tokenizer::{TokenKind, Tokenizer},
+};
+
+const INDENT: &str = "    ";
+
+/// Prints a parsed program in the canonical layout: four-space indentation, one statement
+/// per line, no semicolons, and only the parentheses the operator precedence requires.
+///
+/// Literals are copied from `source` as written, so `0xFF` stays `0xFF`. Doc comments on `func`
+/// and `let` are kept, but other comments are not part of the syntax tree and get lost;
+/// `has_comments` tells whether that would happen.
+pub fn format(program: &Node, source: &str) -> String {
+    let formatter = Formatter { source };
+    let mut out = String::new();
+
+    match &program.kind {
+        NodeKind::Program(statements) => formatter.statements(&mut out, statements, 0),
+        _ => formatter.statements(&mut out, std::slice::from_ref(program), 0),
+    }
+
+    out
+}
+
+/// Whether `source` contains a comment other than a doc comment, which formatting would drop.
+pub fn has_comments(source: &str) -> bool {
+    Tokenizer::new(source).flatten().any(|token| match token.kind {
+        TokenKind::LineComment(text) => !text.starts_with('/') || text.starts_with("//"),
+        TokenKind::BlockComment { .. } => true,
+        _ => false,
+    })
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+}
+
+impl Formatter<'_> {
+    fn statements(&self, out: &mut String, statements: &[Node], depth: usize) {
+        let lines: Vec<_> = statements.iter().map(|a| self.statement(a, depth)).collect();
+
+        for (index, line) in lines.iter().enumerate() {
+            // Keep a single blank line wherever the author left one or more.
+            if index > 0 && first_line(&statements[index]) > statements[index - 1].span.end.line + 1 {
+                out.push('\n');
+            }
+
+            out.push_str(&INDENT.repeat(depth));
+            out.push_str(line);
+
+            // Without a separator, a following `(` or `-` would continue this statement.
+            if lines
+                .get(index + 1)
+                .is_some_and(|next| next.starts_with('(') || next.starts_with('-'))
+            {
+                out.push(';');
+            }
+
+            out.push('\n');
+        }
+    }
+
+    fn block(&self, block: &Node, depth: usize) -> String {
+        let statements = match &block.kind {
+            NodeKind::Block(statements) => statements.as_slice(),
+            _ => std::slice::from_ref(block),
+        };
+
+        if statements.is_empty() {
+            return "{}".to_string();
+        }
+
+        let mut out = "{\n".to_string();
+        self.statements(&mut out, statements, depth + 1);
+        out.push_str(&INDENT.repeat(depth));
+        out.push('}');
+
+        out
+    }
+
+    fn statement(&self, node: &Node, depth: usize) -> String {
+        match &node.kind {
+            NodeKind::Assignment { name, value, doc } => {
+                format!("{}let {name} = {}", doc_comment(doc, depth), self.expression(value))
+            }
+            NodeKind::Reassignment { name, value } => format!("{name} = {}", self.expression(value)),
+            NodeKind::AttributeAssignment {
+                object,
+                attribute,
+                value,
+            } => format!(
+                "{}.{attribute} = {}",
+                self.operand(object, |_| true),
+                self.expression(value)
+            ),
+            NodeKind::Function {
+                name,
+                parameters,
+                code,
+                doc,
+            } => format!(
+                "{}func {name}({}) {}",
+                doc_comment(doc, depth),
+                parameters.join(", "),
+                self.block(code, depth)
+            ),
+            NodeKind::Return(value) => format!("return {}", self.expression(value)),
+            NodeKind::If {
+                condition,
+                alternative,
+                block,
+            } => {
+                let mut out = format!("if {} {}", self.expression(condition), self.block(block, depth));
+
+                match &alternative.kind {
+                    NodeKind::Program(statements) if statements.is_empty() => {}
+                    NodeKind::If { .. } => {
+                        out.push_str(" else ");
+                        out.push_str(&self.statement(alternative, depth));
+                    }
+                    _ => {
+                        out.push_str(" else ");
+                        out.push_str(&self.block(alternative, depth));
+                    }
+                }
+
+                out
+            }
+            NodeKind::While {
+                label,
+                condition,
+                block,
+            } => format!(
+                "{}while {} {}",
+                label_prefix(label),
+                self.expression(condition),
+                self.block(block, depth)
+            ),
+            NodeKind::For {
+                label,
+                variable,
+                iterable,
+                block,
+            } => format!(
+                "{}for {variable} in {} {}",
+                label_prefix(label),
+                self.expression(iterable),
+                self.block(block, depth)
+            ),
+            NodeKind::Break(label) => jump("break", label),
+            NodeKind::Continue(label) => jump("continue", label),
+            NodeKind::Block(_) => self.block(node, depth),
+            NodeKind::Program(statements) => {
+                let mut out = String::new();
+                self.statements(&mut out, statements, depth);
+
+                out.trim().to_string()
+            }
+            _ => self.expression(node),
+        }
+    }
+
+    fn expression(&self, node: &Node) -> String {
+        if let Some((operator, lhs, rhs)) = node.binary() {
+            let precedence = operator.precedence();
+
+            // Operators are left-associative, so only the right operand needs parentheses
+            // at equal precedence.
+            let lhs = self.operand(lhs, |a| a < precedence);
+            let rhs = self.operand(rhs, |a| a <= precedence);
+
+            return match &node.kind {
+                NodeKind::Range(..) => format!("{lhs}..{rhs}"),
+                _ => format!("{lhs} {} {rhs}", operator.symbol()),
+            };
+        }
+
+        match &node.kind {
+            NodeKind::Ident(id) => id.clone(),
+            NodeKind::Number(_) | NodeKind::String(_) | NodeKind::Interpolated(_) => {
+                self.source[node.span.start.offset..node.span.end.offset].to_string()
+            }
+            NodeKind::Not(operand) => format!("!{}", self.operand(operand, |_| true)),
+            NodeKind::Negate(operand) => format!("-{}", self.operand(operand, |_| true)),
+            NodeKind::Call { callee, arguments } => {
+                format!("{}({})", self.expression(callee), self.list(arguments))
+            }
+            NodeKind::AttributeResolve { parent, value } => {
+                format!("{}.{}", self.operand(parent, |_| true), self.expression(value))
+            }
+            NodeKind::Array(elements) => format!("[{}]", self.list(elements)),
+            _ => self.statement(node, 0),
+        }
+    }
+
+    /// Formats an operand, wrapping it in parentheses if it is a binary operation
+    /// whose precedence satisfies `needs_parentheses`.
+    fn operand(&self, node: &Node, needs_parentheses: impl Fn(u8) -> bool) -> String {
+        let text = self.expression(node);
+
+        match node.binary() {
+            Some((operator, ..)) if needs_parentheses(operator.precedence()) => format!("({text})"),
+            _ => text,
+        }
+    }
+
+    fn list(&self, nodes: &[Node]) -> String {
+        nodes.iter().map(|a| self.expression(a)).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// The `///` lines before a declaration, each followed by the indentation of the next line.
+fn doc_comment(doc: &Option<String>, depth: usize) -> String {
+    let Some(doc) = doc else {
+        return String::new();
+    };
+
+    doc.split('\n')
+        .map(|line| match line {
+            "" => format!("///\n{}", INDENT.repeat(depth)),
+            line => format!("/// {line}\n{}", INDENT.repeat(depth)),
+        })
+        .collect()
+}
+
+/// The line a statement starts on, counting its doc comment.
+fn first_line(node: &Node) -> usize {
+    let doc = match &node.kind {
+        NodeKind::Assignment { doc, .. } | NodeKind::Function { doc, .. } => doc.as_deref(),
+        _ => None,
+    };
+
+    node.span.start.line.saturating_sub(doc.map_or(0, |a| a.split('\n').count()))
+}
+
+fn label_prefix(label: &Option<String>) -> String {
+    label.as_ref().map(|a| format!("{a}: ")).unwrap_or_default()
+}
+
+fn jump(keyword: &str, label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("{keyword} {label}"),
+        None => keyword.to_string(),
+    }
+}
@@ -0,0 +1,983 @@
+use std::{cell::RefCell, cmp::Ordering, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    builtins,
+    native::{self, IntoNative},
+    number::{ArithmeticError, Number, Operation},
+    parser::{Node, NodeKind, Part},
+    script_object::ScriptObject,
+    span::Span,
+    vm::Closure,
+};
+
+pub type Env = Rc<RefCell<Scope>>;
+
+/// How many script function calls may be active at once. The VM uses the same
+/// limit, so both backends stop the same runaway recursion at the same call.
+pub(crate) const MAX_CALL_DEPTH: usize = 5_000;
+
+/// How deeply the tree-walking interpreter may nest expressions and statements.
+/// Leaves room for `MAX_CALL_DEPTH` calls of a few levels each, and needs the
+/// larger stack the `maaray` binary runs on; the default 8 MiB only fits about 2000.
+const MAX_EVAL_DEPTH: usize = 50_000;
+
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Rc<Vec<Value>>),
+    /// Half-open range `start..end`.
+    Range(i64, i64),
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    /// A function compiled for the bytecode VM.
+    Closure(Rc<Closure>),
+    Module(Rc<Module>),
+    /// A value owned by the host, see `ScriptObject`.
+    Object(Rc<RefCell<dyn ScriptObject>>),
+}
+
+impl Value {
+    pub fn object<T: ScriptObject + 'static>(object: Rc<RefCell<T>>) -> Self {
+        Value::Object(object)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "bool",
+            Value::Number(n) => n.type_name(),
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Range(..) => "range",
+            Value::Function(_) | Value::Native(_) | Value::Closure(_) => "function",
+            Value::Module(_) => "module",
+            Value::Object(object) => object.try_borrow().map_or("object", |a| a.type_name()),
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => !n.is_zero(),
+            Value::String(s) => !s.is_empty(),
+            Value::Array(items) => !items.is_empty(),
+            _ => true,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            // Compared by value, so `1 == 1.0`.
+            (Value::Number(a), Value::Number(b)) => a.compare(b) == Some(Ordering::Equal),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Range(a, b), Value::Range(c, d)) => a == c && b == d,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write!(f, "{s}"),
+            Value::Array(items) => {
+                write!(f, "[")?;
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{item:?}")?;
+                }
+
+                write!(f, "]")
+            }
+            Value::Range(start, end) => write!(f, "{start}..{end}"),
+            Value::Function(func) => write!(f, "<func {}>", func.name),
+            Value::Native(func) => write!(f, "<native func {}>", func.name),
+            Value::Closure(func) => write!(f, "<func {}>", func.prototype.name),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+            Value::Object(_) => write!(f, "<object {}>", self.type_name()),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{s:?}"),
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+/// A function declared in a script with `func`, together with the scope it was declared in.
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub code: Node,
+    pub closure: Env,
+}
+
+pub type NativeFn = Box<dyn Fn(&[Value]) -> Result<Value, RuntimeError>>;
+
+/// A function implemented in Rust. `arity` of `None` accepts any number of arguments.
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: Option<usize>,
+    pub function: NativeFn,
+}
+
+/// A named collection of values, resolved with `module.member`.
+pub struct Module {
+    pub name: String,
+    pub members: HashMap<String, Value>,
+}
+
+impl Module {
+    /// A copy of `module`, or a new empty module called `name`, with `value` added at the
+    /// dotted `path` below it.
+    fn with_member(module: Option<&Module>, name: &str, path: &str, value: Value) -> Value {
+        let mut members = module.map(|a| a.members.clone()).unwrap_or_default();
+
+        match path.split_once('.') {
+            None => {
+                members.insert(path.to_string(), value);
+            }
+            Some((child, rest)) => {
+                let existing = match members.get(child) {
+                    Some(Value::Module(module)) => Some(Rc::clone(module)),
+                    _ => None,
+                };
+
+                members.insert(child.to_string(), Module::with_member(existing.as_deref(), child, rest, value));
+            }
+        }
+
+        Value::Module(Rc::new(Module {
+            name: name.to_string(),
+            members,
+        }))
+    }
+}
+
+#[derive(Default)]
+pub struct Scope {
+    variables: HashMap<String, Value>,
+    parent: Option<Env>,
+}
+
+impl Scope {
+    pub fn new() -> Env {
+        Rc::new(RefCell::new(Self::default()))
+    }
+
+    pub fn child(parent: &Env) -> Env {
+        Rc::new(RefCell::new(Self {
+            variables: HashMap::new(),
+            parent: Some(Rc::clone(parent)),
+        }))
+    }
+
+    pub fn define(&mut self, name: impl Into<String>, value: Value) {
+        self.variables.insert(name.into(), value);
+    }
+
+    /// Defines `value` at a dotted path like `str.from`, as a member of the module named by
+    /// the first part, creating modules that do not exist yet. Modules are copied rather than
+    /// changed, so scripts holding on to the old one keep seeing it unchanged.
+    pub fn define_path(&mut self, path: &str, value: Value) {
+        let Some((name, rest)) = path.split_once('.') else {
+            self.define(path, value);
+            return;
+        };
+
+        let module = match self.variables.get(name) {
+            Some(Value::Module(module)) => Some(Rc::clone(module)),
+            _ => None,
+        };
+
+        self.define(name, Module::with_member(module.as_deref(), name, rest, value));
+    }
+
+    /// Overwrites an existing variable in the nearest scope that declares it.
+    /// Returns `false` if no scope does.
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        if let Some(slot) = self.variables.get_mut(name) {
+            *slot = value;
+            return true;
+        }
+
+        match &self.parent {
+            Some(parent) => parent.borrow_mut().assign(name, value),
+            None => false,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.variables.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref()?.borrow().get(name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    /// Boxed so that results stay small on the interpreter's deeply recursive paths.
+    kind: Box<RuntimeErrorKind>,
+    span: Option<Span>,
+}
+
+impl RuntimeError {
+    pub fn new(kind: RuntimeErrorKind) -> Self {
+        Self {
+            kind: Box::new(kind),
+            span: None,
+        }
+    }
+
+    /// An error raised by host code, reported with `message` as is.
+    pub fn custom(message: impl Into<String>) -> Self {
+        Self::new(RuntimeErrorKind::Custom(message.into()))
+    }
+
+    pub fn kind(&self) -> &RuntimeErrorKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Attaches `span` unless a more precise one was recorded deeper in the tree.
+    pub fn or_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+}
+
+#[derive(Debug)]
+pub enum RuntimeErrorKind {
+    UndefinedVariable(String),
+    NotCallable(&'static str),
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A native function got an argument it cannot convert to its parameter type.
+    InvalidArgument {
+        function: String,
+        /// 1-based.
+        position: usize,
+        expected: &'static str,
+        found: &'static str,
+    },
+    InvalidOperand {
+        operation: &'static str,
+        operand: &'static str,
+    },
+    InvalidOperands {
+        operation: &'static str,
+        left: &'static str,
+        right: &'static str,
+    },
+    NoAttribute {
+        attribute: String,
+        type_name: &'static str,
+    },
+    ReadOnlyAttribute {
+        attribute: String,
+        type_name: &'static str,
+    },
+    /// A script used a host object while the host held a mutable borrow of it.
+    ObjectInUse,
+    InvalidAttribute,
+    NotIterable(&'static str),
+    /// An integer operation overflowed an `i64`.
+    Overflow(&'static str),
+    DivisionByZero,
+    InvalidConversion {
+        value: String,
+        target: &'static str,
+    },
+    /// `break` or `continue` reached a function boundary.
+    JumpOutsideLoop,
+    /// Calls or expressions nested deeper than the interpreter allows.
+    RecursionLimit,
+    /// `print` or `println` could not write to standard output.
+    Output(std::io::ErrorKind),
+    /// Raised by a native function supplied by the host.
+    Custom(String),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &*self.kind {
+            RuntimeErrorKind::UndefinedVariable(name) => {
+                write!(f, "undefined variable `{name}`")
+            }
+            RuntimeErrorKind::NotCallable(type_name) => {
+                write!(f, "value of type `{type_name}` is not callable")
+            }
+            RuntimeErrorKind::ArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function `{name}` takes {expected} argument(s) but {found} were supplied"
+            ),
+            RuntimeErrorKind::InvalidArgument {
+                function,
+                position,
+                expected,
+                found,
+            } => write!(
+                f,
+                "argument {position} of `{function}` must be `{expected}`, found `{found}`"
+            ),
+            RuntimeErrorKind::InvalidOperand { operation, operand } => {
+                write!(f, "cannot apply `{operation}` to `{operand}`")
+            }
+            RuntimeErrorKind::InvalidOperands {
+                operation,
+                left,
+                right,
+            } => write!(
+                f,
+                "cannot apply `{operation}` to `{left}` and `{right}`"
+            ),
+            RuntimeErrorKind::NoAttribute {
+                attribute,
+                type_name,
+            } => write!(f, "`{type_name}` has no attribute `{attribute}`"),
+            RuntimeErrorKind::ReadOnlyAttribute {
+                attribute,
+                type_name,
+            } => write!(f, "cannot assign to attribute `{attribute}` of `{type_name}`"),
+            RuntimeErrorKind::ObjectInUse => write!(f, "object is already in use by the host"),
+            RuntimeErrorKind::InvalidAttribute => {
+                write!(f, "expected an attribute name or method call after `.`")
+            }
+            RuntimeErrorKind::NotIterable(type_name) => {
+                write!(f, "value of type `{type_name}` is not iterable")
+            }
+            RuntimeErrorKind::Overflow(operation) => {
+                write!(f, "integer overflow in `{operation}`")
+            }
+            RuntimeErrorKind::DivisionByZero => write!(f, "integer division by zero"),
+            RuntimeErrorKind::InvalidConversion { value, target } => {
+                write!(f, "cannot convert {value} to `{target}`")
+            }
+            RuntimeErrorKind::JumpOutsideLoop => {
+                write!(f, "`break` or `continue` outside of a loop")
+            }
+            RuntimeErrorKind::RecursionLimit => write!(f, "maximum recursion depth exceeded"),
+            RuntimeErrorKind::Output(kind) => write!(f, "cannot write output: {kind}"),
+            RuntimeErrorKind::Custom(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Non-local exits that unwind through `Interpreter::eval`.
+enum Unwind {
+    Return(Value),
+    Break(Option<String>),
+    Continue(Option<String>),
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(value: RuntimeError) -> Self {
+        Unwind::Error(value)
+    }
+}
+
+type EvalResult = Result<Value, Unwind>;
+
+#[derive(PartialEq)]
+enum LoopFlow {
+    Continue,
+    Break,
+}
+
+pub struct Interpreter {
+    globals: Env,
+    /// Nesting of `eval` calls, to stop deep recursion before the native stack runs out.
+    depth: usize,
+    /// Script functions currently running.
+    calls: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let globals = Scope::new();
+        builtins::install(&mut globals.borrow_mut());
+
+        Self {
+            globals,
+            depth: 0,
+            calls: 0,
+        }
+    }
+
+    pub fn define_global(&mut self, name: impl Into<String>, value: Value) {
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    /// Makes `function` callable from scripts at `path`, see `native::register`.
+    pub fn register_fn<Args>(&mut self, path: &str, function: impl IntoNative<Args>) {
+        native::register(&mut self.globals.borrow_mut(), path, function);
+    }
+
+    /// Runs a whole program in the global scope and returns the value of its last statement.
+    pub fn run(&mut self, program: &Node) -> Result<Value, RuntimeError> {
+        let globals = Rc::clone(&self.globals);
+
+        match self.eval_statements(program, &globals) {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Break(_) | Unwind::Continue(_)) => {
+                Err(RuntimeError::new(RuntimeErrorKind::JumpOutsideLoop))
+            }
+            Err(Unwind::Error(e)) => Err(e),
+        }
+    }
+
+    /// `Parser::parse` collapses a single statement into the node itself,
+    /// so a body is either a `Program`/`Block` or one bare statement.
+    fn eval_statements(&mut self, node: &Node, env: &Env) -> EvalResult {
+        match &node.kind {
+            NodeKind::Program(nodes) | NodeKind::Block(nodes) => {
+                let mut last = Value::Nil;
+
+                for node in nodes {
+                    last = self.eval(node, env)?;
+                }
+
+                Ok(last)
+            }
+            _ => self.eval(node, env),
+        }
+    }
+
+    fn eval_block(&mut self, node: &Node, env: &Env) -> EvalResult {
+        let scope = Scope::child(env);
+
+        self.eval_statements(node, &scope)
+    }
+
+    fn eval(&mut self, node: &Node, env: &Env) -> EvalResult {
+        if self.depth == MAX_EVAL_DEPTH {
+            return Err(RuntimeError::new(RuntimeErrorKind::RecursionLimit).or_span(node.span).into());
+        }
+
+        self.depth += 1;
+        let result = self.eval_kind(node, env);
+        self.depth -= 1;
+
+        result.map_err(|unwind| match unwind {
+            Unwind::Error(e) => Unwind::Error(e.or_span(node.span)),
+            other => other,
+        })
+    }
+
+    /// Only dispatches, so that the frame every level of nesting adds stays small;
+    /// anything that needs more than a couple of locals lives in its own method.
+    fn eval_kind(&mut self, node: &Node, env: &Env) -> EvalResult {
+        match &node.kind {
+            NodeKind::Ident(name) => env
+                .borrow()
+                .get(name)
+                .ok_or_else(|| RuntimeError::new(RuntimeErrorKind::UndefinedVariable(name.clone())).into()),
+            NodeKind::Number(nr) => Ok(Value::Number(nr.clone())),
+            NodeKind::String(st) => Ok(Value::String(st.clone())),
+            NodeKind::Interpolated(parts) => self.eval_interpolated(parts, env),
+            NodeKind::Assignment { name, value, .. } => self.eval_assignment(name, value, env),
+            NodeKind::Reassignment { name, value } => self.eval_reassignment(name, value, env),
+            NodeKind::AttributeAssignment {
+                object,
+                attribute,
+                value,
+            } => self.eval_attribute_assignment(object, attribute, value, env),
+            NodeKind::Function {
+                name,
+                parameters,
+                code,
+                ..
+            } => {
+                declare_function(name, parameters, code, env);
+
+                Ok(Value::Nil)
+            }
+            NodeKind::Return(value) => self.eval(value, env).and_then(|a| Err(Unwind::Return(a))),
+            NodeKind::Add(lhs, rhs) => self.arithmetic(Operation::Add, lhs, rhs, env),
+            NodeKind::Subtract(lhs, rhs) => self.arithmetic(Operation::Subtract, lhs, rhs, env),
+            NodeKind::Multiply(lhs, rhs) => self.arithmetic(Operation::Multiply, lhs, rhs, env),
+            NodeKind::Divide(lhs, rhs) => self.arithmetic(Operation::Divide, lhs, rhs, env),
+            NodeKind::Modulo(lhs, rhs) => self.arithmetic(Operation::Modulo, lhs, rhs, env),
+            NodeKind::BinOr(lhs, rhs) => self.arithmetic(Operation::BinOr, lhs, rhs, env),
+            NodeKind::BinAnd(lhs, rhs) => self.arithmetic(Operation::BinAnd, lhs, rhs, env),
+            NodeKind::Not(value) => self.eval(value, env).map(|a| Value::Bool(!a.is_truthy())),
+            NodeKind::Negate(value) => self.eval_negate(value, env),
+            NodeKind::Or(lhs, rhs) => self.eval_logic(true, lhs, rhs, env),
+            NodeKind::And(lhs, rhs) => self.eval_logic(false, lhs, rhs, env),
+            NodeKind::Equals(lhs, rhs) => self.eval_equality(true, lhs, rhs, env),
+            NodeKind::NotEquals(lhs, rhs) => self.eval_equality(false, lhs, rhs, env),
+            NodeKind::Less(lhs, rhs) => self.compare("<", lhs, rhs, env, Ordering::is_lt),
+            NodeKind::Greater(lhs, rhs) => self.compare(">", lhs, rhs, env, Ordering::is_gt),
+            NodeKind::LessEquals(lhs, rhs) => self.compare("<=", lhs, rhs, env, Ordering::is_le),
+            NodeKind::GreaterEquals(lhs, rhs) => self.compare(">=", lhs, rhs, env, Ordering::is_ge),
+            NodeKind::Call { callee, arguments } => self.eval_call(callee, arguments, env),
+            NodeKind::AttributeResolve { parent, value } => self.eval_attribute_resolve(parent, value, env),
+            NodeKind::If {
+                condition,
+                alternative,
+                block,
+            } => self.eval_if(condition, block, alternative, env),
+            NodeKind::While {
+                label,
+                condition,
+                block,
+            } => self.eval_while(label, condition, block, env),
+            NodeKind::For {
+                label,
+                variable,
+                iterable,
+                block,
+            } => self.eval_for(label, variable, iterable, block, env),
+            NodeKind::Break(label) => Err(Unwind::Break(label.clone())),
+            NodeKind::Continue(label) => Err(Unwind::Continue(label.clone())),
+            NodeKind::Range(start, end) => self.eval_range(start, end, env),
+            NodeKind::Array(elements) => self.eval_arguments(elements, env).map(|a| Value::Array(Rc::new(a))),
+            NodeKind::Program(_) => self.eval_statements(node, env),
+            NodeKind::Block(_) => self.eval_block(node, env),
+        }
+    }
+
+    fn eval_assignment(&mut self, name: &str, value: &Node, env: &Env) -> EvalResult {
+        let value = self.eval(value, env)?;
+        env.borrow_mut().define(name, value);
+
+        Ok(Value::Nil)
+    }
+
+    fn eval_interpolated(&mut self, parts: &[Part], env: &Env) -> EvalResult {
+        let mut string = String::new();
+
+        for part in parts {
+            match part {
+                Part::Literal(literal) => string.push_str(literal),
+                Part::Expression(expression) => {
+                    string.push_str(&self.eval(expression, env)?.to_string())
+                }
+            }
+        }
+
+        Ok(Value::String(string))
+    }
+
+    fn eval_reassignment(&mut self, name: &str, value: &Node, env: &Env) -> EvalResult {
+        let value = self.eval(value, env)?;
+
+        if !env.borrow_mut().assign(name, value) {
+            return Err(RuntimeError::new(RuntimeErrorKind::UndefinedVariable(
+                name.to_string(),
+            ))
+            .into());
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn eval_attribute_assignment(
+        &mut self,
+        object: &Node,
+        attribute: &str,
+        value: &Node,
+        env: &Env,
+    ) -> EvalResult {
+        let object = self.eval(object, env)?;
+        let value = self.eval(value, env)?;
+        set_attribute(&object, attribute, value)?;
+
+        Ok(Value::Nil)
+    }
+
+    fn eval_negate(&mut self, value: &Node, env: &Env) -> EvalResult {
+        match self.eval(value, env)? {
+            Value::Number(nr) => nr
+                .negate()
+                .map(Value::Number)
+                .map_err(|_| RuntimeError::new(RuntimeErrorKind::Overflow("-")).into()),
+            other => Err(RuntimeError::new(RuntimeErrorKind::InvalidOperand {
+                operation: "-",
+                operand: other.type_name(),
+            })
+            .into()),
+        }
+    }
+
+    /// `||` when `or` is set, `&&` otherwise; the right side only runs if it decides the result.
+    fn eval_logic(&mut self, or: bool, lhs: &Node, rhs: &Node, env: &Env) -> EvalResult {
+        if self.eval(lhs, env)?.is_truthy() == or {
+            return Ok(Value::Bool(or));
+        }
+
+        Ok(Value::Bool(self.eval(rhs, env)?.is_truthy()))
+    }
+
+    fn eval_equality(&mut self, equal: bool, lhs: &Node, rhs: &Node, env: &Env) -> EvalResult {
+        let (lhs, rhs) = (self.eval(lhs, env)?, self.eval(rhs, env)?);
+
+        Ok(Value::Bool((lhs == rhs) == equal))
+    }
+
+    fn eval_call(&mut self, callee: &Node, arguments: &[Node], env: &Env) -> EvalResult {
+        let callee = self.eval(callee, env)?;
+        let arguments = self.eval_arguments(arguments, env)?;
+
+        Ok(self.call(&callee, &arguments)?)
+    }
+
+    fn eval_attribute_resolve(&mut self, parent: &Node, value: &Node, env: &Env) -> EvalResult {
+        let parent = self.eval(parent, env)?;
+
+        self.resolve_attribute(parent, value, env)
+    }
+
+    fn eval_if(&mut self, condition: &Node, block: &Node, alternative: &Node, env: &Env) -> EvalResult {
+        if self.eval(condition, env)?.is_truthy() {
+            self.eval_block(block, env)
+        } else {
+            self.eval_block(alternative, env)
+        }
+    }
+
+    fn eval_while(
+        &mut self,
+        label: &Option<String>,
+        condition: &Node,
+        block: &Node,
+        env: &Env,
+    ) -> EvalResult {
+        while self.eval(condition, env)?.is_truthy() {
+            if self.eval_loop_body(label, block, env)? == LoopFlow::Break {
+                break;
+            }
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn eval_for(
+        &mut self,
+        label: &Option<String>,
+        variable: &str,
+        iterable: &Node,
+        block: &Node,
+        env: &Env,
+    ) -> EvalResult {
+        let iterable = self.eval(iterable, env)?;
+
+        for item in iterate(iterable)? {
+            let scope = Scope::child(env);
+            scope.borrow_mut().define(variable, item);
+
+            if self.eval_loop_body(label, block, &scope)? == LoopFlow::Break {
+                break;
+            }
+        }
+
+        Ok(Value::Nil)
+    }
+
+    fn eval_range(&mut self, start: &Node, end: &Node, env: &Env) -> EvalResult {
+        let (start, end) = (self.eval(start, env)?, self.eval(end, env)?);
+
+        Ok(range(&start, &end)?)
+    }
+
+    /// Runs one iteration of a loop labelled `label`, consuming the
+    /// `break`/`continue` aimed at it and passing any other unwind through.
+    fn eval_loop_body(
+        &mut self,
+        label: &Option<String>,
+        block: &Node,
+        env: &Env,
+    ) -> Result<LoopFlow, Unwind> {
+        let targets = |target: &Option<String>| target.is_none() || target == label;
+
+        match self.eval_block(block, env) {
+            Ok(_) => Ok(LoopFlow::Continue),
+            Err(Unwind::Break(target)) if targets(&target) => Ok(LoopFlow::Break),
+            Err(Unwind::Continue(target)) if targets(&target) => Ok(LoopFlow::Continue),
+            Err(other) => Err(other),
+        }
+    }
+
+    fn eval_arguments(&mut self, arguments: &[Node], env: &Env) -> Result<Vec<Value>, Unwind> {
+        arguments.iter().map(|a| self.eval(a, env)).collect()
+    }
+
+    fn arithmetic(&mut self, operation: Operation, lhs: &Node, rhs: &Node, env: &Env) -> EvalResult {
+        let (lhs, rhs) = (self.eval(lhs, env)?, self.eval(rhs, env)?);
+
+        match (operation, lhs, rhs) {
+            (Operation::Add, Value::String(a), Value::String(b)) => Ok(Value::String(a + &b)),
+            (_, a, b) => Ok(arithmetic(operation, &a, &b)?),
+        }
+    }
+
+    fn compare(
+        &mut self,
+        operation: &'static str,
+        lhs: &Node,
+        rhs: &Node,
+        env: &Env,
+        test: fn(Ordering) -> bool,
+    ) -> EvalResult {
+        let (lhs, rhs) = (self.eval(lhs, env)?, self.eval(rhs, env)?);
+
+        Ok(compare(operation, &lhs, &rhs, test)?)
+    }
+
+    /// The parser nests attribute chains to the right: `a.b.c()` is
+    /// `AttributeResolve { a, AttributeResolve { b, Call { c } } }`.
+    fn resolve_attribute(&mut self, object: Value, attribute: &Node, env: &Env) -> EvalResult {
+        match &attribute.kind {
+            NodeKind::Ident(name) => Ok(get_attribute(&object, name)?),
+            NodeKind::Call { callee, arguments } => {
+                let Some(name) = callee.ident() else {
+                    return Err(RuntimeError::new(RuntimeErrorKind::InvalidAttribute).into());
+                };
+
+                let method = get_attribute(&object, name)?;
+                let arguments = self.eval_arguments(arguments, env)?;
+
+                Ok(self.call(&method, &arguments)?)
+            }
+            NodeKind::AttributeResolve { parent, value } => {
+                let object = self.resolve_attribute(object, parent, env)?;
+
+                self.resolve_attribute(object, value, env)
+            }
+            _ => Err(RuntimeError::new(RuntimeErrorKind::InvalidAttribute).into()),
+        }
+    }
+
+    pub fn call(&mut self, callee: &Value, arguments: &[Value]) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::Function(function) => {
+                check_arity(&function.name, function.parameters.len(), arguments.len())?;
+
+                if self.calls == MAX_CALL_DEPTH {
+                    return Err(RuntimeError::new(RuntimeErrorKind::RecursionLimit));
+                }
+
+                let scope = Scope::child(&function.closure);
+
+                for (name, value) in function.parameters.iter().zip(arguments) {
+                    scope.borrow_mut().define(name.clone(), value.clone());
+                }
+
+                self.calls += 1;
+                let result = self.eval_statements(&function.code, &scope);
+                self.calls -= 1;
+
+                match result {
+                    Ok(_) => Ok(Value::Nil),
+                    Err(Unwind::Return(value)) => Ok(value),
+                    Err(Unwind::Break(_) | Unwind::Continue(_)) => {
+                        Err(RuntimeError::new(RuntimeErrorKind::JumpOutsideLoop))
+                    }
+                    Err(Unwind::Error(e)) => Err(e),
+                }
+            }
+            Value::Native(function) => {
+                if let Some(arity) = function.arity {
+                    check_arity(&function.name, arity, arguments.len())?;
+                }
+
+                (function.function)(arguments)
+            }
+            other => Err(RuntimeError::new(RuntimeErrorKind::NotCallable(
+                other.type_name(),
+            ))),
+        }
+    }
+}
+
+/// Defines the function declared by a `func` statement in `env`.
+fn declare_function(name: &str, parameters: &[String], code: &Node, env: &Env) {
+    let function = Function {
+        name: name.to_string(),
+        parameters: parameters.to_vec(),
+        code: code.clone(),
+        closure: Rc::clone(env),
+    };
+
+    env.borrow_mut()
+        .define(name, Value::Function(Rc::new(function)));
+}
+
+/// Reads `object.name`. Methods of host objects come back as native functions bound to the object.
+pub(crate) fn get_attribute(object: &Value, name: &str) -> Result<Value, RuntimeError> {
+    let attribute = match object {
+        Value::Module(module) => module.members.get(name).cloned(),
+        Value::Object(shared) => {
+            let borrowed = shared.try_borrow().map_err(|_| in_use())?;
+
+            match borrowed.get(name) {
+                Some(value) => Some(value),
+                None if borrowed.has_method(name) => Some(bound_method(shared, name)),
+                None => None,
+            }
+        }
+        _ => None,
+    };
+
+    attribute.ok_or_else(|| {
+        RuntimeError::new(RuntimeErrorKind::NoAttribute {
+            attribute: name.to_string(),
+            type_name: object.type_name(),
+        })
+    })
+}
+
+/// Writes `object.name = value`. Module members cannot be changed from scripts.
+pub(crate) fn set_attribute(object: &Value, name: &str, value: Value) -> Result<(), RuntimeError> {
+    let attribute = name.to_string();
+    let type_name = object.type_name();
+
+    match object {
+        Value::Object(shared) => shared.try_borrow_mut().map_err(|_| in_use())?.set(name, value),
+        Value::Module(module) if module.members.contains_key(name) => {
+            Err(RuntimeError::new(RuntimeErrorKind::ReadOnlyAttribute { attribute, type_name }))
+        }
+        _ => Err(RuntimeError::new(RuntimeErrorKind::NoAttribute { attribute, type_name })),
+    }
+}
+
+fn bound_method(object: &Rc<RefCell<dyn ScriptObject>>, name: &str) -> Value {
+    let object = Rc::clone(object);
+    let method = name.to_string();
+
+    Value::Native(Rc::new(NativeFunction {
+        name: name.to_string(),
+        arity: None,
+        function: Box::new(move |arguments| {
+            object.try_borrow_mut().map_err(|_| in_use())?.call_method(&method, arguments)
+        }),
+    }))
+}
+
+fn in_use() -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::ObjectInUse)
+}
+
+/// The values a `for` loop visits: numbers in a range, array elements or characters of a string.
+pub(crate) fn iterate(value: Value) -> Result<Box<dyn Iterator<Item = Value>>, RuntimeError> {
+    match value {
+        Value::Range(start, end) => Ok(Box::new(
+            (start..end).map(|a| Value::Number(Number::Int(a))),
+        )),
+        Value::Array(items) => Ok(Box::new(
+            (0..items.len()).map(move |i| items[i].clone()),
+        )),
+        Value::String(string) => Ok(Box::new(
+            string
+                .chars()
+                .map(|ch| Value::String(ch.to_string()))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )),
+        other => Err(RuntimeError::new(RuntimeErrorKind::NotIterable(
+            other.type_name(),
+        ))),
+    }
+}
+
+pub(crate) fn check_arity(name: &str, expected: usize, found: usize) -> Result<(), RuntimeError> {
+    if expected != found {
+        return Err(RuntimeError::new(RuntimeErrorKind::ArgumentCount {
+            name: name.to_string(),
+            expected,
+            found,
+        }));
+    }
+
+    Ok(())
+}
+
+/// Applies a numeric operator. `+` on strings is handled by the caller.
+pub(crate) fn arithmetic(operation: Operation, lhs: &Value, rhs: &Value) -> Result<Value, RuntimeError> {
+    let (Value::Number(a), Value::Number(b)) = (lhs, rhs) else {
+        return Err(invalid_operands(operation.symbol(), lhs, rhs));
+    };
+
+    a.apply(operation, b).map(Value::Number).map_err(|e| match e {
+        ArithmeticError::Overflow => RuntimeError::new(RuntimeErrorKind::Overflow(operation.symbol())),
+        ArithmeticError::DivisionByZero => RuntimeError::new(RuntimeErrorKind::DivisionByZero),
+        ArithmeticError::Unsupported => invalid_operands(operation.symbol(), lhs, rhs),
+    })
+}
+
+pub(crate) fn compare(
+    operation: &'static str,
+    lhs: &Value,
+    rhs: &Value,
+    test: fn(Ordering) -> bool,
+) -> Result<Value, RuntimeError> {
+    let ordering = match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => a.compare(b),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => return Err(invalid_operands(operation, lhs, rhs)),
+    };
+
+    // NaN is unordered and compares false with everything.
+    Ok(Value::Bool(ordering.map(test).unwrap_or(false)))
+}
+
+/// Ranges only span integers that fit in an `i64`.
+pub(crate) fn range(start: &Value, end: &Value) -> Result<Value, RuntimeError> {
+    match (start, end) {
+        (Value::Number(Number::Int(a)), Value::Number(Number::Int(b))) => Ok(Value::Range(*a, *b)),
+        _ => Err(invalid_operands("..", start, end)),
+    }
+}
+
+pub(crate) fn invalid_operands(operation: &'static str, lhs: &Value, rhs: &Value) -> RuntimeError {
+    RuntimeError::new(RuntimeErrorKind::InvalidOperands {
+        operation,
+        left: lhs.type_name(),
+        right: rhs.type_name(),
+    })
+}
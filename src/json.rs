@@ -0,0 +1,299 @@
+use std::fmt;
+
+use maaray::{
+    lexer::{Lexem, LexemKind, LexemPart},
+    number::Number,
+    parser::{Node, NodeKind, Part},
+    span::{Position, Span},
+};
+
+/// A JSON document, written out compactly by its `Display` impl.
+pub enum Json {
+    Null,
+    /// Kept as text so big integers survive unchanged.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(items) => {
+                write!(f, "[")?;
+
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write!(f, "{item}")?;
+                }
+
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+fn string(value: impl Into<String>) -> Json {
+    Json::String(value.into())
+}
+
+fn position(position: Position) -> Json {
+    Json::Object(vec![
+        ("offset", Json::Number(position.offset.to_string())),
+        ("line", Json::Number(position.line.to_string())),
+        ("column", Json::Number(position.column.to_string())),
+    ])
+}
+
+fn span(span: Span) -> Json {
+    Json::Object(vec![("start", position(span.start)), ("end", position(span.end))])
+}
+
+fn number(number: &Number) -> Json {
+    match number {
+        Number::Float(value) if !value.is_finite() => string(number.to_string()),
+        _ => Json::Number(number.to_string()),
+    }
+}
+
+fn optional(value: &Option<String>) -> Json {
+    value.as_ref().map_or(Json::Null, string)
+}
+
+fn nodes(nodes: &[Node]) -> Json {
+    Json::Array(nodes.iter().map(node).collect())
+}
+
+pub fn lexems(lexems: &[Lexem]) -> Json {
+    Json::Array(lexems.iter().map(lexem).collect())
+}
+
+fn lexem(lexem: &Lexem) -> Json {
+    let (kind, value) = match lexem.token() {
+        LexemKind::Ident(id) => ("Ident", string(id)),
+        LexemKind::Number(nr) => ("Number", number(nr)),
+        LexemKind::StringLiteral(st) => ("String", string(st)),
+        LexemKind::Interpolated(parts) => {
+            let parts = parts
+                .iter()
+                .map(|part| match part {
+                    LexemPart::Literal(literal) => string(literal),
+                    LexemPart::Hole(hole) => lexems(hole),
+                })
+                .collect();
+
+            ("Interpolated", Json::Array(parts))
+        }
+        symbol => ("Symbol", string(symbol.to_string())),
+    };
+
+    Json::Object(vec![("kind", string(kind)), ("value", value), ("span", span(lexem.span()))])
+}
+
+/// The syntax tree as JSON, built recursively; trees from the parser are never
+/// deeper than it allows.
+pub fn node(node: &Node) -> Json {
+    let mut fields = Vec::new();
+
+    let kind = if let Some((operator, lhs, rhs)) = node.binary() {
+        fields.push(("operator", string(operator.symbol())));
+        fields.push(("lhs", self::node(lhs)));
+        fields.push(("rhs", self::node(rhs)));
+
+        "Binary"
+    } else {
+        match &node.kind {
+            NodeKind::Ident(id) => {
+                fields.push(("name", string(id)));
+                "Ident"
+            }
+            NodeKind::Number(nr) => {
+                fields.push(("value", number(nr)));
+                "Number"
+            }
+            NodeKind::String(st) => {
+                fields.push(("value", string(st)));
+                "String"
+            }
+            NodeKind::Interpolated(parts) => {
+                let parts = parts
+                    .iter()
+                    .map(|part| match part {
+                        Part::Literal(literal) => string(literal),
+                        Part::Expression(expression) => self::node(expression),
+                    })
+                    .collect();
+
+                fields.push(("parts", Json::Array(parts)));
+                "Interpolated"
+            }
+            NodeKind::Assignment { name, value, doc } => {
+                fields.push(("name", string(name)));
+                fields.push(("value", self::node(value)));
+                fields.push(("doc", optional(doc)));
+                "Assignment"
+            }
+            NodeKind::Reassignment { name, value } => {
+                fields.push(("name", string(name)));
+                fields.push(("value", self::node(value)));
+                "Reassignment"
+            }
+            NodeKind::AttributeAssignment {
+                object,
+                attribute,
+                value,
+            } => {
+                fields.push(("object", self::node(object)));
+                fields.push(("attribute", string(attribute)));
+                fields.push(("value", self::node(value)));
+                "AttributeAssignment"
+            }
+            NodeKind::Function {
+                name,
+                parameters,
+                code,
+                doc,
+            } => {
+                fields.push(("name", string(name)));
+                fields.push(("parameters", Json::Array(parameters.iter().map(string).collect())));
+                fields.push(("code", self::node(code)));
+                fields.push(("doc", optional(doc)));
+                "Function"
+            }
+            NodeKind::Return(value) => {
+                fields.push(("value", self::node(value)));
+                "Return"
+            }
+            NodeKind::Not(operand) => {
+                fields.push(("operand", self::node(operand)));
+                "Not"
+            }
+            NodeKind::Negate(operand) => {
+                fields.push(("operand", self::node(operand)));
+                "Negate"
+            }
+            NodeKind::Call { callee, arguments } => {
+                fields.push(("callee", self::node(callee)));
+                fields.push(("arguments", nodes(arguments)));
+                "Call"
+            }
+            NodeKind::AttributeResolve { parent, value } => {
+                fields.push(("parent", self::node(parent)));
+                fields.push(("value", self::node(value)));
+                "AttributeResolve"
+            }
+            NodeKind::If {
+                condition,
+                alternative,
+                block,
+            } => {
+                fields.push(("condition", self::node(condition)));
+                fields.push(("block", self::node(block)));
+                fields.push(("alternative", self::node(alternative)));
+                "If"
+            }
+            NodeKind::While {
+                label,
+                condition,
+                block,
+            } => {
+                fields.push(("label", optional(label)));
+                fields.push(("condition", self::node(condition)));
+                fields.push(("block", self::node(block)));
+                "While"
+            }
+            NodeKind::For {
+                label,
+                variable,
+                iterable,
+                block,
+            } => {
+                fields.push(("label", optional(label)));
+                fields.push(("variable", string(variable)));
+                fields.push(("iterable", self::node(iterable)));
+                fields.push(("block", self::node(block)));
+                "For"
+            }
+            NodeKind::Break(label) => {
+                fields.push(("label", optional(label)));
+                "Break"
+            }
+            NodeKind::Continue(label) => {
+                fields.push(("label", optional(label)));
+                "Continue"
+            }
+            NodeKind::Array(elements) => {
+                fields.push(("elements", nodes(elements)));
+                "Array"
+            }
+            NodeKind::Program(statements) => {
+                fields.push(("statements", nodes(statements)));
+                "Program"
+            }
+            NodeKind::Block(statements) => {
+                fields.push(("statements", nodes(statements)));
+                "Block"
+            }
+            NodeKind::Range(..)
+            | NodeKind::Or(..)
+            | NodeKind::And(..)
+            | NodeKind::BinOr(..)
+            | NodeKind::BinAnd(..)
+            | NodeKind::Equals(..)
+            | NodeKind::NotEquals(..)
+            | NodeKind::Less(..)
+            | NodeKind::Greater(..)
+            | NodeKind::LessEquals(..)
+            | NodeKind::GreaterEquals(..)
+            | NodeKind::Add(..)
+            | NodeKind::Subtract(..)
+            | NodeKind::Multiply(..)
+            | NodeKind::Divide(..)
+            | NodeKind::Modulo(..) => unreachable!("binary operations are handled above"),
+        }
+    };
+
+    fields.insert(0, ("kind", string(kind)));
+    fields.push(("span", span(node.span)));
+
+    Json::Object(fields)
+}
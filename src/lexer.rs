@@ -1,46 +1,200 @@
-use crate::tokenizer::{TResult, Token, TokenKind};
+use std::{fmt, iter::Peekable};
+
+use crate::{
+    number::Number,
+    span::Span,
+    tokenizer::{TResult, Token, TokenKind, TokenPart, TokenizerError},
+};
+
+pub type LResult<T> = Result<T, LexError>;
+
+/// Why a piece of the input could not be turned into a lexem. The lexer keeps
+/// going after an error, so every problem in the input gets reported.
+#[derive(Debug)]
+pub enum LexError {
+    Tokenizer(TokenizerError),
+    /// A character that is not part of the language, like `#` or `?`.
+    UnknownCharacter(char, Span),
+    InvalidNumber(Span),
+    /// A `/*` comment that is never closed.
+    UnterminatedComment(Span),
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::Tokenizer(e) => e.span(),
+            LexError::UnknownCharacter(_, span)
+            | LexError::InvalidNumber(span)
+            | LexError::UnterminatedComment(span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::Tokenizer(e) => write!(f, "{e}"),
+            LexError::UnknownCharacter(ch, _) => write!(f, "unknown character {ch:?}"),
+            LexError::InvalidNumber(_) => write!(f, "invalid number literal"),
+            LexError::UnterminatedComment(_) => write!(f, "unterminated block comment"),
+        }
+    }
+}
+
+impl std::error::Error for LexError {}
+
+impl From<TokenizerError> for LexError {
+    fn from(e: TokenizerError) -> Self {
+        LexError::Tokenizer(e)
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum LexemKind {
     Ident(String),
-    Number(f64),
+    Number(Number),
     StringLiteral(String),
+    Interpolated(Vec<LexemPart>),
     LParen,
     RParen,
     LBrace,
     RBrace,
+    LBracket,
+    RBracket,
     Dot,
     Comma,
     Colon,
     Semicolon,
     Equals,
+    Exclamation,
     Or,
     And,
     Less,
     Greater,
     Slash,
     Asterisk,
+    Percent,
     Minus,
     Plus,
+    DoubleDot,
+    DoubleEquals,
+    NotEquals,
+    LessEquals,
+    GreaterEquals,
+    DoubleOr,
+    DoubleAnd,
+    Arrow,
+    PlusEquals,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexemPart {
+    Literal(String),
+    Hole(Vec<Lexem>),
+}
+
+impl fmt::Display for LexemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexemKind::Ident(id) => write!(f, "{id}"),
+            LexemKind::Number(nr) => write!(f, "{nr}"),
+            LexemKind::StringLiteral(st) => write!(f, "{st:?}"),
+            LexemKind::Interpolated(parts) => {
+                write!(f, "$\"")?;
+
+                for part in parts {
+                    match part {
+                        LexemPart::Literal(literal) => {
+                            let escaped = format!("{literal:?}");
+                            let escaped = &escaped[1..escaped.len() - 1];
+
+                            write!(f, "{}", escaped.replace('{', "{{").replace('}', "}}"))?;
+                        }
+                        LexemPart::Hole(lexems) => {
+                            let hole: Vec<_> = lexems.iter().map(|a| a.token().to_string()).collect();
+
+                            write!(f, "{{{}}}", hole.join(" "))?;
+                        }
+                    }
+                }
+
+                write!(f, "\"")
+            }
+            LexemKind::LParen => write!(f, "("),
+            LexemKind::RParen => write!(f, ")"),
+            LexemKind::LBrace => write!(f, "{{"),
+            LexemKind::RBrace => write!(f, "}}"),
+            LexemKind::LBracket => write!(f, "["),
+            LexemKind::RBracket => write!(f, "]"),
+            LexemKind::Dot => write!(f, "."),
+            LexemKind::Comma => write!(f, ","),
+            LexemKind::Colon => write!(f, ":"),
+            LexemKind::Semicolon => write!(f, ";"),
+            LexemKind::Equals => write!(f, "="),
+            LexemKind::Exclamation => write!(f, "!"),
+            LexemKind::Or => write!(f, "|"),
+            LexemKind::And => write!(f, "&"),
+            LexemKind::Less => write!(f, "<"),
+            LexemKind::Greater => write!(f, ">"),
+            LexemKind::Slash => write!(f, "/"),
+            LexemKind::Asterisk => write!(f, "*"),
+            LexemKind::Percent => write!(f, "%"),
+            LexemKind::Minus => write!(f, "-"),
+            LexemKind::Plus => write!(f, "+"),
+            LexemKind::DoubleDot => write!(f, ".."),
+            LexemKind::DoubleEquals => write!(f, "=="),
+            LexemKind::NotEquals => write!(f, "!="),
+            LexemKind::LessEquals => write!(f, "<="),
+            LexemKind::GreaterEquals => write!(f, ">="),
+            LexemKind::DoubleOr => write!(f, "||"),
+            LexemKind::DoubleAnd => write!(f, "&&"),
+            LexemKind::Arrow => write!(f, "->"),
+            LexemKind::PlusEquals => write!(f, "+="),
+        }
+    }
+}
+
+/// Source text without meaning to the parser, kept by a lexer created `with_trivia`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    Comment,
+    DocComment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Lexem {
-    line: usize,
-    column: usize,
+    span: Span,
     kind: LexemKind,
+    /// The `///` doc comment directly before this lexem.
+    doc: Option<String>,
+    /// Whitespace and comments between the previous lexem and this one.
+    trivia: Vec<Trivia>,
 }
 
 impl Lexem {
-    pub fn line(&self) -> usize {
-        self.line
-    }
-    pub fn column(&self) -> usize {
-        self.column
+    pub fn span(&self) -> Span {
+        self.span
     }
     pub fn token(&self) -> &LexemKind {
         &self.kind
     }
+    /// The lines of the `///` comments before this lexem, without the slashes.
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+    /// The trivia before this lexem; always empty unless the lexer keeps trivia.
+    pub fn trivia(&self) -> &[Trivia] {
+        &self.trivia
+    }
     pub fn is_ident(&self) -> bool {
         matches!(self.kind, LexemKind::Ident(_))
     }
@@ -60,90 +214,299 @@ impl Lexem {
 }
 
 pub struct Lexer<T: Iterator> {
-    input: T,
+    input: Peekable<T>,
+    /// Doc comment lines read so far, waiting for the lexem they document.
+    doc: Option<String>,
+    keep_trivia: bool,
+    /// Trivia read so far, waiting for the lexem that follows it.
+    trivia: Vec<Trivia>,
 }
 
 impl<T: Iterator<Item = TResult<Token>>> Lexer<T> {
     pub fn new(input: T) -> Self {
-        Self { input }
+        Self {
+            input: input.peekable(),
+            doc: None,
+            keep_trivia: false,
+            trivia: Vec::new(),
+        }
+    }
+
+    /// Attaches whitespace, line breaks and comments to the lexem after them instead of
+    /// dropping them. Spaces only show up if the tokenizer was created `with_trivia` too.
+    pub fn with_trivia(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
+
+    /// The trivia after the last lexem, once the input is exhausted.
+    pub fn trailing_trivia(&self) -> &[Trivia] {
+        &self.trivia
+    }
+
+    fn skip(&mut self, kind: TriviaKind, span: Span) {
+        if self.keep_trivia {
+            self.trivia.push(Trivia { kind, span });
+        }
+    }
+
+    /// Adds a line to the pending doc comment if `comment` is one. Only `///` starts
+    /// a doc comment; `//` and `////…` are ordinary comments.
+    fn doc_line(&mut self, comment: &str) -> bool {
+        let Some(line) = comment.strip_prefix('/').filter(|a| !a.starts_with('/')) else {
+            return false;
+        };
+
+        let line = line.strip_prefix(' ').unwrap_or(line);
+
+        match &mut self.doc {
+            Some(doc) => {
+                doc.push('\n');
+                doc.push_str(line);
+            }
+            None => self.doc = Some(line.to_string()),
+        }
+
+        true
     }
 
-    pub fn next(&mut self) -> Option<TResult<Token>> {
+    fn next_token(&mut self) -> Option<TResult<Token>> {
         self.input.next()
     }
 
-    pub fn lex(&mut self) -> Option<TResult<Lexem>> {
-        let next = self.next();
-
-        match next {
-            None => None,
-            Some(token) => match token {
-                Ok(token) => {
-                    let kind = match token.kind {
-                        TokenKind::Ident(id) => LexemKind::Ident(id),
-                        TokenKind::StringLiteral(st) => LexemKind::StringLiteral(st),
-                        TokenKind::Number(nr) => {
-                            LexemKind::Number(nr.parse().expect("Failed to convert string to f64"))
-                        },
-                        TokenKind::Symbol(sym) => match sym {
-                            '\n' | ' ' => return self.lex(),
-                            '(' => LexemKind::LParen,
-                            ')' => LexemKind::RParen,
-                            '{' => LexemKind::LBrace,
-                            '}' => LexemKind::RBrace,
-                            '=' => LexemKind::Equals,
-                            '|' => LexemKind::Or,
-                            '&' => LexemKind::And,
-                            '<' => LexemKind::Less,
-                            '>' => LexemKind::Greater,
-                            ';' => LexemKind::Semicolon,
-                            '+' => LexemKind::Plus,
-                            '-' => LexemKind::Minus,
-                            '*' => LexemKind::Asterisk,
-                            '/' => {
-                            	let nx = self.next();
-
-                            	if let Some(Ok(tok)) = nx {
-                            		if tok.kind == TokenKind::Symbol('/') {
-                            			let mut next = self.next();
-                            			while let Some(Ok(tok)) = next {
-                            				if tok.kind == TokenKind::Symbol('\n') {
-                            					break;
-                            				}
-
-                            				next = self.next();
-                            			}
-
-                            			return self.lex();
-                            		}
-                            	}
-                            	
-                            	LexemKind::Slash
+    /// Consumes `second` if it directly follows the symbol spanning `span`, without
+    /// whitespace or comments in between, and extends `span` over it. Operators are
+    /// matched greedily, so `===` is `==` followed by `=`.
+    fn follows(&mut self, second: char, span: &mut Span) -> bool {
+        let adjacent = matches!(
+            self.input.peek(),
+            Some(Ok(token)) if token.kind == TokenKind::Symbol(second)
+                && token.span.start.offset == span.end.offset
+        );
+
+        if !adjacent {
+            return false;
+        }
+
+        if let Some(Ok(token)) = self.next_token() {
+            *span = span.to(token.span);
+        }
+
+        true
+    }
+
+    pub fn lex(&mut self) -> Option<LResult<Lexem>> {
+        // Trivia is consumed in a loop rather than by recursion, so long runs of
+        // comments or blank lines cannot overflow the stack.
+        loop {
+            let token = match self.next_token()? {
+                Ok(token) => token,
+                Err(x) => return Some(Err(x.into())),
+            };
+
+            let mut span = token.span;
+
+            let kind = match token.kind {
+                TokenKind::Ident(id) => LexemKind::Ident(id),
+                TokenKind::StringLiteral(st) => LexemKind::StringLiteral(st),
+                TokenKind::Interpolated(parts) => {
+                    let mut lexem_parts = Vec::new();
+
+                    for part in parts {
+                        lexem_parts.push(match part {
+                            TokenPart::Literal(literal) => LexemPart::Literal(literal),
+                            TokenPart::Hole(tokens) => match lex_hole(tokens) {
+                                Ok(lexems) => LexemPart::Hole(lexems),
+                                Err(e) => return Some(Err(e)),
                             },
-                            '.' => LexemKind::Dot,
-                            ',' => LexemKind::Comma,
-                            _ => {
-                                todo!("Not supported yet: {sym:?}");
-                            }
-                        },
+                        });
+                    }
+
+                    LexemKind::Interpolated(lexem_parts)
+                }
+                TokenKind::Number(nr) => match Number::parse(&nr) {
+                    Some(number) => LexemKind::Number(number),
+                    None => return Some(Err(LexError::InvalidNumber(token.span))),
+                },
+                TokenKind::LineComment(comment) => {
+                    let kind = if self.doc_line(&comment) {
+                        TriviaKind::DocComment
+                    } else {
+                        TriviaKind::Comment
                     };
 
-                    Some(Ok(Lexem {
-                        line: token.line,
-                        column: token.column,
-                        kind,
-                    }))
+                    self.skip(kind, span);
+                    continue;
+                }
+                TokenKind::BlockComment { closed: true, .. } => {
+                    self.skip(TriviaKind::Comment, span);
+                    continue;
+                }
+                TokenKind::BlockComment { closed: false, .. } => {
+                    return Some(Err(LexError::UnterminatedComment(token.span)));
                 }
-                Err(x) => Some(Err(x)),
-            },
+                TokenKind::Whitespace => {
+                    self.skip(TriviaKind::Whitespace, span);
+                    continue;
+                }
+                TokenKind::Symbol(sym) => match sym {
+                    '\n' => {
+                        self.skip(TriviaKind::Newline, span);
+                        continue;
+                    }
+                    '(' => LexemKind::LParen,
+                    ')' => LexemKind::RParen,
+                    '{' => LexemKind::LBrace,
+                    '}' => LexemKind::RBrace,
+                    '[' => LexemKind::LBracket,
+                    ']' => LexemKind::RBracket,
+                    '=' if self.follows('=', &mut span) => LexemKind::DoubleEquals,
+                    '=' => LexemKind::Equals,
+                    '!' if self.follows('=', &mut span) => LexemKind::NotEquals,
+                    '!' => LexemKind::Exclamation,
+                    '|' if self.follows('|', &mut span) => LexemKind::DoubleOr,
+                    '|' => LexemKind::Or,
+                    '&' if self.follows('&', &mut span) => LexemKind::DoubleAnd,
+                    '&' => LexemKind::And,
+                    '<' if self.follows('=', &mut span) => LexemKind::LessEquals,
+                    '<' => LexemKind::Less,
+                    '>' if self.follows('=', &mut span) => LexemKind::GreaterEquals,
+                    '>' => LexemKind::Greater,
+                    ';' => LexemKind::Semicolon,
+                    '+' if self.follows('=', &mut span) => LexemKind::PlusEquals,
+                    '+' => LexemKind::Plus,
+                    '-' if self.follows('>', &mut span) => LexemKind::Arrow,
+                    '-' => LexemKind::Minus,
+                    '*' => LexemKind::Asterisk,
+                    '%' => LexemKind::Percent,
+                    '/' => LexemKind::Slash,
+                    '.' if self.follows('.', &mut span) => LexemKind::DoubleDot,
+                    '.' => LexemKind::Dot,
+                    ':' => LexemKind::Colon,
+                    ',' => LexemKind::Comma,
+                    _ => return Some(Err(LexError::UnknownCharacter(sym, token.span))),
+                },
+            };
+
+            return Some(Ok(Lexem {
+                span,
+                kind,
+                doc: self.doc.take(),
+                trivia: std::mem::take(&mut self.trivia),
+            }));
         }
     }
 }
 
+/// Lexes the tokens of an interpolation hole on their own.
+fn lex_hole(tokens: Vec<Token>) -> LResult<Vec<Lexem>> {
+    let tokens = tokens.into_iter().map(Ok as fn(Token) -> TResult<Token>);
+
+    Lexer::new(tokens).collect()
+}
+
 impl<T: Iterator<Item = TResult<Token>>> Iterator for Lexer<T> {
-    type Item = TResult<Lexem>;
+    type Item = LResult<Lexem>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.lex()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    fn lexems(code: &str) -> Vec<Lexem> {
+        Lexer::new(Tokenizer::new(code)).map(Result::unwrap).collect()
+    }
+
+    /// Every lexem's kind with its span as `(byte range, start column, end column)`.
+    fn spans(code: &str) -> Vec<(LexemKind, std::ops::Range<usize>, usize, usize)> {
+        lexems(code)
+            .into_iter()
+            .map(|a| (a.kind, a.span.start.offset..a.span.end.offset, a.span.start.column, a.span.end.column))
+            .collect()
+    }
+
+    #[test]
+    fn operators_span_all_their_characters() {
+        assert_eq!(
+            spans("a<=b == c->d"),
+            [
+                (LexemKind::Ident("a".to_string()), 0..1, 1, 2),
+                (LexemKind::LessEquals, 1..3, 2, 4),
+                (LexemKind::Ident("b".to_string()), 3..4, 4, 5),
+                (LexemKind::DoubleEquals, 5..7, 6, 8),
+                (LexemKind::Ident("c".to_string()), 8..9, 9, 10),
+                (LexemKind::Arrow, 9..11, 10, 12),
+                (LexemKind::Ident("d".to_string()), 11..12, 12, 13),
+            ]
+        );
+
+        // Only adjacent symbols combine.
+        assert_eq!(spans("= ="), [(LexemKind::Equals, 0..1, 1, 2), (LexemKind::Equals, 2..3, 3, 4)]);
+        assert_eq!(spans("1..2")[1], (LexemKind::DoubleDot, 1..3, 2, 4));
+    }
+
+    #[test]
+    fn spans_after_multibyte_characters() {
+        assert_eq!(
+            spans("\"ü\" != ñ"),
+            [
+                (LexemKind::StringLiteral("ü".to_string()), 0..4, 1, 4),
+                (LexemKind::NotEquals, 5..7, 5, 7),
+                (LexemKind::Ident("ñ".to_string()), 8..10, 8, 9),
+            ]
+        );
+
+        let error = Lexer::new(Tokenizer::new("ü ? 1")).find_map(Result::err).unwrap();
+        assert!(matches!(error, LexError::UnknownCharacter('?', _)));
+        assert_eq!((error.span().start.offset, error.span().column()), (3, 3));
+    }
+
+    #[test]
+    fn lexems_after_comments_and_lines_keep_their_positions() {
+        let lexems = lexems("/* ä */ x\n/// doc\n  y");
+
+        assert_eq!((lexems[0].span.line(), lexems[0].span.column(), lexems[0].span.start.offset), (1, 9, 9));
+        assert_eq!((lexems[1].span.line(), lexems[1].span.column(), lexems[1].span.start.offset), (3, 3, 21));
+        assert_eq!(lexems[1].doc(), Some("doc"));
+    }
+
+    #[test]
+    fn trivia_spans_fill_the_gaps_between_lexems() {
+        let code = "a  // c\n\tb";
+        let lexems: Vec<_> = Lexer::new(Tokenizer::new(code).with_trivia()).with_trivia().map(Result::unwrap).collect();
+
+        let trivia: Vec<_> = lexems[1].trivia().iter().map(|a| (a.kind, a.span.start.offset..a.span.end.offset)).collect();
+        assert_eq!(
+            trivia,
+            [
+                (TriviaKind::Whitespace, 1..3),
+                (TriviaKind::Comment, 3..7),
+                (TriviaKind::Newline, 7..8),
+                (TriviaKind::Whitespace, 8..9),
+            ]
+        );
+        assert_eq!(lexems[1].span.start.offset..lexems[1].span.end.offset, 9..10);
+    }
+
+    #[test]
+    fn hole_lexems_point_into_the_string() {
+        let lexems = lexems("$\"é{a+1}\"");
+
+        let LexemKind::Interpolated(parts) = &lexems[0].kind else {
+            panic!("expected an interpolated string");
+        };
+
+        let [LexemPart::Literal(_), LexemPart::Hole(hole)] = parts.as_slice() else {
+            panic!("expected a literal and a hole, found {parts:?}");
+        };
+
+        let hole: Vec<_> = hole.iter().map(|a| (a.span.start.offset, a.span.column())).collect();
+        assert_eq!(hole, [(5, 5), (6, 6), (7, 7)]);
+        assert_eq!(lexems[0].span.end.offset, 10);
+    }
+}
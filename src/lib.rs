@@ -0,0 +1,105 @@
+//! The maaray scripting language: its front end, a tree-walking interpreter,
+//! a bytecode compiler and the VM that runs it.
+//!
+//! Most programs only need [`parse`] to check code and [`Engine`] to run it:
+//!
+//! ```
+//! let ast = maaray::parse("let answer = 6 * 7").unwrap();
+//! let mut engine = maaray::Engine::new();
+//!
+//! engine.run(&ast).unwrap();
+//! assert_eq!(engine.eval("answer").unwrap().to_string(), "42");
+//!
+//! let errors = maaray::parse("let = 1").unwrap_err();
+//! assert_eq!(errors.to_string(), "1:5: expected variable name after `let`");
+//! ```
+//!
+//! The modules give access to every stage on its own, for tools like the
+//! `maaray` command line.
+
+pub mod tokenizer;
+pub mod lexer;
+pub mod parser;
+pub mod cst;
+mod cursor;
+pub mod interpreter;
+mod builtins;
+pub mod native;
+pub mod script_object;
+pub mod diagnostics;
+pub mod span;
+pub mod number;
+pub mod bigint;
+pub mod bytecode;
+pub mod compiler;
+pub mod vm;
+pub mod disasm;
+pub mod object;
+pub mod check;
+pub mod formatter;
+pub mod trace;
+mod engine;
+
+pub use crate::diagnostics::{Diagnostic, Diagnostics};
+pub use crate::engine::Engine;
+pub use crate::interpreter::{RuntimeError, Value};
+pub use crate::native::{FromValue, IntoValue};
+pub use crate::script_object::ScriptObject;
+
+use crate::{
+    lexer::{LResult, Lexem, Lexer},
+    parser::Parser,
+    tokenizer::Tokenizer,
+};
+
+/// A parsed program: the root node of its syntax tree.
+pub type Ast = parser::Node;
+
+/// Splits `code` into lexems, reporting every malformed token.
+pub fn lex(code: &str) -> Result<Vec<Lexem>, Diagnostics> {
+    collect_lexems(&mut Lexer::new(Tokenizer::new(code)))
+}
+
+fn collect_lexems(lexer: &mut impl Iterator<Item = LResult<Lexem>>) -> Result<Vec<Lexem>, Diagnostics> {
+    let (lexems, errors): (Vec<_>, Vec<_>) = lexer.partition(|a| a.is_ok());
+
+    if !errors.is_empty() {
+        return Err(errors.iter().filter_map(|a| a.as_ref().err()).map(Diagnostic::from).collect());
+    }
+
+    Ok(lexems.into_iter().filter_map(|a| a.ok()).collect())
+}
+
+/// Parses `code` into a syntax tree.
+pub fn parse(code: &str) -> Result<Ast, Diagnostics> {
+    parse_lexems(lex(code)?)
+}
+
+fn parse_lexems(lexems: Vec<Lexem>) -> Result<Ast, Diagnostics> {
+    Parser::new(lexems)
+        .parse()
+        .map_err(|errors| errors.iter().map(Diagnostic::from).collect())
+}
+
+/// Parses `code` into a lossless syntax tree that keeps every space and comment,
+/// so printing it gives back `code` exactly.
+///
+/// ```
+/// use maaray::cst::SyntaxKind;
+///
+/// let code = "let  x = 1 // one\nprintln( x )\n";
+/// let tree = maaray::parse_lossless(code).unwrap();
+///
+/// assert_eq!(tree.to_string(), code);
+///
+/// let call = tree.child_nodes().nth(1).unwrap();
+/// assert_eq!(call.kind(), SyntaxKind::Call);
+/// assert_eq!(call.to_string(), "println( x )");
+/// ```
+pub fn parse_lossless(code: &str) -> Result<cst::SyntaxNode, Diagnostics> {
+    let mut lexer = Lexer::new(Tokenizer::new(code).with_trivia()).with_trivia();
+    let lexems = collect_lexems(&mut lexer)?;
+    let ast = parse_lexems(lexems.clone())?;
+
+    Ok(cst::build(code, &ast, &lexems, lexer.trailing_trivia()))
+}
@@ -1,48 +1,365 @@
-mod tokenizer;
-mod lexer;
-mod parser;
-mod cursor;
+mod json;
+mod repl;
+mod tree;
 
-use crate::parser::Node;
+use std::{
+	fmt::Display,
+	io::{ErrorKind, IsTerminal, Read, Write},
+	path::Path,
+	process::exit,
+	rc::Rc,
+};
 
-const CODE: &str = include_str!("../maaray-examples/fibonacci.mry");
+use maaray::bytecode::Prototype;
+use maaray::diagnostics::Renderer;
+use maaray::trace::TreeTracer;
+use maaray::interpreter::{Interpreter, RuntimeErrorKind};
+use maaray::{Ast, Diagnostic, Diagnostics, RuntimeError, Value};
+use maaray::{check, compiler, disasm, formatter, object, parser, vm};
 
-fn parse_to_ast(code: &str) -> Node {
-	let tokenizer = tokenizer::Tokenizer::new(code);
-	let lexer = lexer::Lexer::new(tokenizer);
-	let tokens: Vec<_> = lexer.collect();
+const USAGE: &str = "\
+Usage: maaray [command] [options] <file> [args...]
 
-	for i in &tokens {
-	    if let Err(e) = i {
-	        eprintln!("Tokenizing error at {}:{}", e.line(), e.column());
-	    }
+Commands:
+    run [--vm] <file> [args...]   run a script, the default command; `args` holds the arguments
+    check <file>                  look for errors without running the script
+    tokens [--json] <file>        print the lexems of a script
+    ast [--json] <file>           print the syntax tree of a script
+    cst <file>                    print the lossless syntax tree of a script, with spaces and comments
+    fmt <file>                    print a script in the canonical layout
+    compile <file>                write the bytecode of a script next to it, as <file>.mryc
+    disasm <file>                 print the bytecode of a script or of a .mryc file
+
+Every command that parses also accepts --trace-parser, which prints the rules
+the parser tries, and whether they matched, to standard error.
+
+`maaray --compile <file>` and `maaray --disasm <file>` still work as the older
+spellings of `compile` and `disasm`.
+
+Without arguments, starts an interactive session. A <file> of `-` reads standard input.
+A .mryc file is run or disassembled directly, always on the VM.
+
+Exit status: 0 on success, 64 for bad usage, 65 for syntax or check errors,
+70 for runtime errors and 74 when a file cannot be read or written.";
+
+// Exit statuses, as in the BSD `sysexits.h`.
+const EXIT_USAGE: i32 = 64;
+const EXIT_SYNTAX: i32 = 65;
+const EXIT_RUNTIME: i32 = 70;
+const EXIT_IO: i32 = 74;
+
+/// The stack of the thread everything runs on. The tree-walking interpreter
+/// recurses for every call a script makes, and its limits are sized for this.
+const STACK_SIZE: usize = 256 << 20;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+	Run,
+	Check,
+	Tokens,
+	Ast,
+	Cst,
+	Fmt,
+	Compile,
+	Disasm,
+}
+
+struct Options {
+	command: Command,
+	vm: bool,
+	json: bool,
+	trace_parser: bool,
+	file: String,
+	/// Everything after the file, handed to the script as `args`.
+	args: Vec<String>,
+}
+
+impl Options {
+	fn parse(mut arguments: Vec<String>) -> Result<Self, String> {
+		let command = match arguments.first().map(String::as_str) {
+			Some("run") => Some(Command::Run),
+			Some("check") => Some(Command::Check),
+			Some("tokens") => Some(Command::Tokens),
+			Some("ast") => Some(Command::Ast),
+			Some("cst") => Some(Command::Cst),
+			Some("fmt") => Some(Command::Fmt),
+			Some("compile") => Some(Command::Compile),
+			Some("disasm") => Some(Command::Disasm),
+			_ => None,
+		};
+
+		// `maaray file.mry` is short for `maaray run file.mry`.
+		let implicit = command.is_none();
+		let mut command = match command {
+			Some(command) => {
+				arguments.remove(0);
+				command
+			}
+			None => Command::Run,
+		};
+
+		let mut arguments = arguments.into_iter();
+		let mut vm = false;
+		let mut json = false;
+		let mut trace_parser = false;
+		let mut file = None;
+
+		for argument in arguments.by_ref() {
+			match argument.as_str() {
+				"--vm" if command == Command::Run => vm = true,
+				// The flags that came before the `compile` and `disasm` commands.
+				"--compile" if implicit => command = Command::Compile,
+				"--disasm" if implicit => command = Command::Disasm,
+				"--json" if matches!(command, Command::Tokens | Command::Ast) => json = true,
+				"--trace-parser" if !matches!(command, Command::Tokens | Command::Cst) => trace_parser = true,
+				option if option.starts_with("--") => return Err(format!("unknown option `{option}`")),
+				_ => {
+					file = Some(argument);
+					break;
+				}
+			}
+		}
+
+		let file = file.ok_or("missing input file")?;
+		let args: Vec<_> = arguments.collect();
+
+		if let Some(extra) = args.first()
+			&& command != Command::Run
+		{
+			return Err(format!("unexpected argument `{extra}`"));
+		}
+
+		Ok(Self {
+			command,
+			vm,
+			json,
+			trace_parser,
+			file,
+			args,
+		})
+	}
+
+	fn file_name(&self) -> &str {
+		if self.file == "-" { "<stdin>" } else { &self.file }
+	}
+}
+
+fn parse(code: &str, trace: bool) -> Result<Ast, Diagnostics> {
+	if !trace {
+		return maaray::parse(code);
+	}
+
+	parser::Parser::new(maaray::lex(code)?)
+		.with_tracer(Box::new(TreeTracer::new()))
+		.parse()
+		.map_err(|errors| errors.iter().map(Diagnostic::from).collect())
+}
+
+fn report(renderer: &Renderer, diagnostics: &Diagnostics, status: i32) -> ! {
+	eprint!("{}", diagnostics.render(renderer));
+	exit(status);
+}
+
+fn fail(message: impl Display, status: i32) -> ! {
+	eprintln!("error: {message}");
+	exit(status);
+}
+
+/// Writes the output of a command. A reader that stops early, like `head`, ends
+/// the program quietly.
+fn output(text: impl Display) {
+	let mut stdout = std::io::stdout().lock();
+
+	match write!(stdout, "{text}").and_then(|()| stdout.flush()) {
+		Ok(()) => {}
+		Err(e) if e.kind() == ErrorKind::BrokenPipe => exit(0),
+		Err(e) => fail(format_args!("cannot write output: {e}"), EXIT_IO),
 	}
+}
+
+/// Reports a runtime error, except for a script whose output was cut off the same way.
+fn runtime_error(renderer: &Renderer, e: &RuntimeError) -> ! {
+	if let RuntimeErrorKind::Output(ErrorKind::BrokenPipe) = e.kind() {
+		exit(0);
+	}
+
+	report(renderer, &Diagnostic::from(e).into(), EXIT_RUNTIME)
+}
+
+/// Reads a script from `file`, or from standard input for `-`.
+fn read_source(file: &str) -> String {
+	let mut code = String::new();
+
+	let result = if file == "-" {
+		std::io::stdin().read_to_string(&mut code).map(|_| ())
+	} else {
+		std::fs::read_to_string(file).map(|a| code = a)
+	};
+
+	if let Err(e) = result {
+		fail(format_args!("cannot read `{file}`: {e}"), EXIT_IO);
+	}
+
+	code
+}
 
-	let tokens = tokens.into_iter().map(|a| a.unwrap()).collect();
+fn script_arguments(args: &[String]) -> Value {
+	Value::Array(Rc::new(args.iter().cloned().map(Value::String).collect()))
+}
 
-	println!("{:?}", tokens);
+/// Runs a compiled program on the VM, or prints its disassembly.
+fn run_compiled(renderer: &Renderer, options: &Options, program: Rc<Prototype>) {
+	if options.command == Command::Disasm {
+		output(disasm::disassemble(&program));
+		return;
+	}
 
-	let mut parser = parser::Parser::new(tokens);
-	let ast = parser.parse();
+	let mut vm = vm::Vm::new();
+	vm.define_global("args", script_arguments(&options.args));
 
-	ast
+	if let Err(e) = vm.run(program) {
+		runtime_error(renderer, &e);
+	}
 }
 
 fn main() {
-	let filename = match std::env::args().skip(1).next() {
-		Some(x) => x,
-		None => {
-			eprintln!("Usage: {} code.mry", std::env::args().nth(0).unwrap());
-			std::process::exit(1);
+	let thread = std::thread::Builder::new()
+		.stack_size(STACK_SIZE)
+		.spawn(run)
+		.expect("cannot start the main thread");
+
+	// A panic has already been reported by the thread itself.
+	if thread.join().is_err() {
+		exit(101);
+	}
+}
+
+fn run() {
+	let arguments: Vec<String> = std::env::args().skip(1).collect();
+
+	if arguments.is_empty() {
+		match repl::Repl::new().run() {
+			Err(e) if e.kind() != ErrorKind::BrokenPipe => fail(e, EXIT_IO),
+			_ => {}
+		}
+
+		return;
+	}
+
+	if matches!(arguments[0].as_str(), "help" | "-h" | "--help") {
+		output(format_args!("{USAGE}\n"));
+		return;
+	}
+
+	let options = match Options::parse(arguments) {
+		Ok(options) => options,
+		Err(message) => {
+			eprintln!("error: {message}\n\n{USAGE}");
+			exit(EXIT_USAGE);
 		}
 	};
 
-	let code = std::fs::read_to_string(filename).expect("File error");
+	let colors = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+
+	// Compiled programs skip the whole front end and always run on the VM.
+	if options.file != "-" && Path::new(&options.file).extension().is_some_and(|a| a == "mryc") {
+		if !matches!(options.command, Command::Run | Command::Disasm) {
+			fail(format_args!("`{}` is compiled bytecode, not a script", options.file), EXIT_USAGE);
+		}
+
+		let renderer = Renderer::new(&options.file, "").colored(colors);
+		let bytes = std::fs::read(&options.file)
+			.unwrap_or_else(|e| fail(format_args!("cannot read `{}`: {e}", options.file), EXIT_IO));
+
+		let program = match object::read(&bytes) {
+			Ok(program) => Rc::new(program),
+			Err(e) => report(&renderer, &Diagnostic::error(e.to_string()).into(), EXIT_SYNTAX),
+		};
+
+		run_compiled(&renderer, &options, program);
+		return;
+	}
+
+	let code = read_source(&options.file);
+	let renderer = Renderer::new(options.file_name(), &code).colored(colors);
 
-	println!("Code: \n{}", &code);
-	println!("----------------------");
+	if options.command == Command::Tokens {
+		let lexems = maaray::lex(&code).unwrap_or_else(|diagnostics| report(&renderer, &diagnostics, EXIT_SYNTAX));
 
-	let ast = parse_to_ast(&code);
+		if options.json {
+			output(format_args!("{}\n", json::lexems(&lexems)));
+			return;
+		}
+
+		for lexem in lexems {
+			let span = lexem.span();
+			output(format_args!("{}:{}\t{:?}\n", span.line(), span.column(), lexem.token()));
+		}
+
+		return;
+	}
+
+	if options.command == Command::Cst {
+		let tree = maaray::parse_lossless(&code).unwrap_or_else(|diagnostics| report(&renderer, &diagnostics, EXIT_SYNTAX));
+		output(format_args!("{tree:?}"));
+
+		return;
+	}
+
+	let ast = parse(&code, options.trace_parser).unwrap_or_else(|diagnostics| report(&renderer, &diagnostics, EXIT_SYNTAX));
+
+	match options.command {
+		Command::Ast if options.json => output(format_args!("{}\n", json::node(&ast))),
+		Command::Ast => output(tree::node(&ast)),
+		Command::Fmt => {
+			if formatter::has_comments(&code) {
+				eprintln!("warning: comments are not preserved by `fmt`");
+			}
+
+			output(formatter::format(&ast, &code));
+		}
+		Command::Check => {
+			let mut diagnostics = check::check(&ast, &["args"]);
+
+			if let Err(e) = compiler::compile(&ast) {
+				diagnostics.push(Diagnostic::from(&e));
+			}
+
+			if !diagnostics.is_empty() {
+				report(&renderer, &diagnostics.into_iter().collect(), EXIT_SYNTAX);
+			}
+		}
+		Command::Run if !options.vm => {
+			let mut interpreter = Interpreter::new();
+			interpreter.define_global("args", script_arguments(&options.args));
+
+			if let Err(e) = interpreter.run(&ast) {
+				runtime_error(&renderer, &e);
+			}
+		}
+		Command::Run | Command::Compile | Command::Disasm => {
+			let program = match compiler::compile(&ast) {
+				Ok(program) => program,
+				Err(e) => report(&renderer, &Diagnostic::from(&e).into(), EXIT_SYNTAX),
+			};
 
-    println!("Tokens: {:#?}", ast);
+			if options.command != Command::Compile {
+				run_compiled(&renderer, &options, program);
+				return;
+			}
+
+			let bytes = object::write(&program);
+
+			let result = if options.file == "-" {
+				std::io::stdout().write_all(&bytes)
+			} else {
+				std::fs::write(Path::new(&options.file).with_extension("mryc"), bytes)
+			};
+
+			if let Err(e) = result {
+				fail(format_args!("cannot write bytecode: {e}"), EXIT_IO);
+			}
+		}
+		Command::Tokens | Command::Cst => unreachable!("handled before parsing"),
+	}
 }
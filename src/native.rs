@@ -0,0 +1,268 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    interpreter::{self, NativeFunction, RuntimeError, RuntimeErrorKind, Scope, Value},
+    number::Number,
+    script_object::ScriptObject,
+};
+
+/// Defines `function` in `scope` under `path`. A dotted path like `str.from` adds it to
+/// the module `str`, which scripts then reach through attribute access.
+pub fn register<Args>(scope: &mut Scope, path: &str, function: impl IntoNative<Args>) {
+    let name = path.rsplit('.').next().unwrap_or(path);
+    let native = function.into_native(name);
+
+    scope.define_path(path, Value::Native(Rc::new(native)));
+}
+
+/// A Rust type a native function can take as a parameter.
+pub trait FromValue: Sized {
+    /// The script type name used in error messages.
+    const TYPE_NAME: &'static str;
+
+    /// Converts `value`, or returns `None` if it has the wrong type.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+/// A Rust type a native function can return.
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl FromValue for Value {
+    const TYPE_NAME: &'static str = "any";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromValue for bool {
+    const TYPE_NAME: &'static str = "bool";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+/// Only `int`s and `bigint`s small enough to fit convert; floats are never truncated.
+impl FromValue for i64 {
+    const TYPE_NAME: &'static str = "int";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(Number::Int(a)) => Some(*a),
+            Value::Number(Number::BigInt(a)) => a.to_i64(),
+            _ => None,
+        }
+    }
+}
+
+/// Any number converts, rounding big integers to the nearest float.
+impl FromValue for f64 {
+    const TYPE_NAME: &'static str = "float";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(number) => Some(number.to_f64()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Number {
+    const TYPE_NAME: &'static str = "number";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Number(number) => Some(number.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    const TYPE_NAME: &'static str = "string";
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::Number(Number::Int(self))
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Number(Number::Float(self))
+    }
+}
+
+impl IntoValue for Number {
+    fn into_value(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::Array(Rc::new(self.into_iter().map(IntoValue::into_value).collect()))
+    }
+}
+
+/// `None` becomes `nil`.
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::Nil, IntoValue::into_value)
+    }
+}
+
+/// Shares the object with scripts; the host keeps seeing every change they make.
+impl<T: ScriptObject + 'static> IntoValue for Rc<RefCell<T>> {
+    fn into_value(self) -> Value {
+        Value::object(self)
+    }
+}
+
+/// What a native function may return: a value, or a `Result` for functions that can fail.
+pub trait IntoResult {
+    fn into_result(self) -> Result<Value, RuntimeError>;
+}
+
+impl<T: IntoValue> IntoResult for T {
+    fn into_result(self) -> Result<Value, RuntimeError> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue> IntoResult for Result<T, RuntimeError> {
+    fn into_result(self) -> Result<Value, RuntimeError> {
+        self.map(IntoValue::into_value)
+    }
+}
+
+/// A Rust function that can be called from scripts. `Args` only tells the
+/// implementations apart: it is `Variadic` for functions taking the raw
+/// argument slice and the tuple of parameter types otherwise.
+pub trait IntoNative<Args> {
+    fn into_native(self, name: &str) -> NativeFunction;
+}
+
+/// Marks functions that take `&[Value]` and check their arguments themselves.
+pub struct Variadic;
+
+impl<F> IntoNative<Variadic> for F
+where
+    F: Fn(&[Value]) -> Result<Value, RuntimeError> + 'static,
+{
+    fn into_native(self, name: &str) -> NativeFunction {
+        NativeFunction {
+            name: name.to_string(),
+            arity: None,
+            function: Box::new(self),
+        }
+    }
+}
+
+/// A tuple of parameter types that a raw argument slice can be converted to.
+pub trait FromArguments: Sized {
+    fn from_arguments(function: &str, arguments: &[Value]) -> Result<Self, RuntimeError>;
+}
+
+/// Checks the count and types of `arguments` passed to `function`, for methods of
+/// a `ScriptObject` and other functions that receive the raw argument slice.
+pub fn arguments<T: FromArguments>(function: &str, arguments: &[Value]) -> Result<T, RuntimeError> {
+    T::from_arguments(function, arguments)
+}
+
+fn argument<T: FromValue>(function: &str, index: usize, value: &Value) -> Result<T, RuntimeError> {
+    T::from_value(value).ok_or_else(|| {
+        RuntimeError::new(RuntimeErrorKind::InvalidArgument {
+            function: function.to_string(),
+            position: index + 1,
+            expected: T::TYPE_NAME,
+            found: value.type_name(),
+        })
+    })
+}
+
+macro_rules! impl_into_native {
+    ($count:literal; $($parameter:ident $index:tt),*) => {
+        impl<F, R, $($parameter),*> IntoNative<($($parameter,)*)> for F
+        where
+            F: Fn($($parameter),*) -> R + 'static,
+            R: IntoResult,
+            $($parameter: FromValue,)*
+        {
+            #[allow(unused_variables)]
+            fn into_native(self, name: &str) -> NativeFunction {
+                let function_name = name.to_string();
+
+                NativeFunction {
+                    name: name.to_string(),
+                    arity: Some($count),
+                    // The caller has already checked that there are exactly `$count` arguments.
+                    function: Box::new(move |arguments: &[Value]| {
+                        self($(argument::<$parameter>(&function_name, $index, &arguments[$index])?),*)
+                            .into_result()
+                    }),
+                }
+            }
+        }
+
+        impl<$($parameter: FromValue),*> FromArguments for ($($parameter,)*) {
+            #[allow(unused_variables)]
+            fn from_arguments(function: &str, arguments: &[Value]) -> Result<Self, RuntimeError> {
+                interpreter::check_arity(function, $count, arguments.len())?;
+
+                Ok(($(argument::<$parameter>(function, $index, &arguments[$index])?,)*))
+            }
+        }
+    };
+}
+
+impl_into_native!(0;);
+impl_into_native!(1; A 0);
+impl_into_native!(2; A 0, B 1);
+impl_into_native!(3; A 0, B 1, C 2);
+impl_into_native!(4; A 0, B 1, C 2, D 3);
+impl_into_native!(5; A 0, B 1, C 2, D 3, E 4);
@@ -0,0 +1,177 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::bigint::BigInt;
+
+/// A numeric literal or value. Integers are `i64` and only become `BigInt`
+/// when written too large for one or converted with `bigint.from`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Number {
+    Int(i64),
+    BigInt(BigInt),
+    Float(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    BinOr,
+    BinAnd,
+}
+
+impl Operation {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operation::Add => "+",
+            Operation::Subtract => "-",
+            Operation::Multiply => "*",
+            Operation::Divide => "/",
+            Operation::Modulo => "%",
+            Operation::BinOr => "|",
+            Operation::BinAnd => "&",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArithmeticError {
+    Overflow,
+    DivisionByZero,
+    /// The operation is not defined for these kinds of numbers, like `|` on floats.
+    Unsupported,
+}
+
+impl Number {
+    /// Parses a literal as produced by the tokenizer: decimal digits with an
+    /// optional fraction and exponent, separators already removed.
+    pub fn parse(text: &str) -> Option<Self> {
+        if text.contains(['.', 'e', 'E']) {
+            return text.parse().ok().map(Number::Float);
+        }
+
+        match text.parse() {
+            Ok(int) => Some(Number::Int(int)),
+            Err(_) => BigInt::parse(text).map(Number::BigInt),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Number::Int(_) => "int",
+            Number::BigInt(_) => "bigint",
+            Number::Float(_) => "float",
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Int(a) => *a == 0,
+            Number::BigInt(a) => a.is_zero(),
+            Number::Float(a) => *a == 0.0,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Int(a) => *a as f64,
+            Number::BigInt(a) => a.to_f64(),
+            Number::Float(a) => *a,
+        }
+    }
+
+    fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Number::Int(a) => Some(BigInt::from(*a)),
+            Number::BigInt(a) => Some(a.clone()),
+            Number::Float(_) => None,
+        }
+    }
+
+    /// Integer operations are checked: overflowing an `i64` is an error rather
+    /// than a silent wrap or promotion. A float on either side makes the result a float.
+    /// `/` and `%` on integers truncate towards zero, like Rust.
+    pub fn apply(&self, operation: Operation, other: &Number) -> Result<Number, ArithmeticError> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => int_operation(operation, *a, *b).map(Number::Int),
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                float_operation(operation, self.to_f64(), other.to_f64()).map(Number::Float)
+            }
+            _ => match (self.to_bigint(), other.to_bigint()) {
+                (Some(a), Some(b)) => bigint_operation(operation, &a, &b).map(Number::BigInt),
+                _ => Err(ArithmeticError::Unsupported),
+            },
+        }
+    }
+
+    pub fn negate(&self) -> Result<Number, ArithmeticError> {
+        match self {
+            Number::Int(a) => a.checked_neg().map(Number::Int).ok_or(ArithmeticError::Overflow),
+            Number::BigInt(a) => Ok(Number::BigInt(-a)),
+            Number::Float(a) => Ok(Number::Float(-a)),
+        }
+    }
+
+    /// Compares numbers of any kind by value. `None` if either side is NaN.
+    pub fn compare(&self, other: &Number) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(b)),
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                self.to_f64().partial_cmp(&other.to_f64())
+            }
+            _ => Some(self.to_bigint()?.cmp(&other.to_bigint()?)),
+        }
+    }
+}
+
+fn int_operation(operation: Operation, a: i64, b: i64) -> Result<i64, ArithmeticError> {
+    let result = match operation {
+        Operation::Add => a.checked_add(b),
+        Operation::Subtract => a.checked_sub(b),
+        Operation::Multiply => a.checked_mul(b),
+        Operation::Divide | Operation::Modulo if b == 0 => {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        Operation::Divide => a.checked_div(b),
+        Operation::Modulo => a.checked_rem(b),
+        Operation::BinOr => Some(a | b),
+        Operation::BinAnd => Some(a & b),
+    };
+
+    result.ok_or(ArithmeticError::Overflow)
+}
+
+fn float_operation(operation: Operation, a: f64, b: f64) -> Result<f64, ArithmeticError> {
+    match operation {
+        Operation::Add => Ok(a + b),
+        Operation::Subtract => Ok(a - b),
+        Operation::Multiply => Ok(a * b),
+        Operation::Divide => Ok(a / b),
+        Operation::Modulo => Ok(a % b),
+        Operation::BinOr | Operation::BinAnd => Err(ArithmeticError::Unsupported),
+    }
+}
+
+fn bigint_operation(operation: Operation, a: &BigInt, b: &BigInt) -> Result<BigInt, ArithmeticError> {
+    match operation {
+        Operation::Add => Ok(a + b),
+        Operation::Subtract => Ok(a - b),
+        Operation::Multiply => Ok(a * b),
+        Operation::Divide => a.checked_div(b).ok_or(ArithmeticError::DivisionByZero),
+        Operation::Modulo => a.checked_rem(b).ok_or(ArithmeticError::DivisionByZero),
+        Operation::BinOr | Operation::BinAnd => Err(ArithmeticError::Unsupported),
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(a) => write!(f, "{a}"),
+            Number::BigInt(a) => write!(f, "{a}"),
+            // `Debug` keeps the `.0` on whole floats, so `2.0` and `2` print differently.
+            Number::Float(a) => write!(f, "{a:?}"),
+        }
+    }
+}
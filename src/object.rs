@@ -0,0 +1,647 @@
+//! The `.mryc` object format: a compiled program that the VM can run without the source.
+//!
+//! All integers are little-endian. A file is the magic `MRYC`, a `u16` format
+//! version and the script's prototype. A prototype is written as:
+//!
+//! - name, arity, slot count, captured parameters and captures
+//! - constant pool: tagged ints, big ints, floats and strings
+//! - names used by globals and attributes
+//! - function table: the nested prototypes, recursively
+//! - code: one opcode byte per instruction and its operand, if any
+//! - line table: the source span of every instruction
+//!
+//! `read` rejects files the VM could trip over: operands out of range, code that can
+//! run off its end and code that would pop values or iterators that are not there.
+
+use std::{fmt, rc::Rc};
+
+use crate::{
+    bigint::BigInt,
+    bytecode::{Capture, Chunk, Instruction, Prototype},
+    interpreter::Value,
+    number::{Number, Operation},
+    span::{Position, Span},
+};
+
+pub const MAGIC: &[u8; 4] = b"MRYC";
+pub const VERSION: u16 = 1;
+
+const OPERATIONS: [Operation; 7] = [
+    Operation::Add,
+    Operation::Subtract,
+    Operation::Multiply,
+    Operation::Divide,
+    Operation::Modulo,
+    Operation::BinOr,
+    Operation::BinAnd,
+];
+
+#[derive(Debug)]
+pub struct ObjectError {
+    /// Byte offset into the file where decoding failed.
+    offset: usize,
+    kind: ObjectErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ObjectErrorKind {
+    BadMagic,
+    UnsupportedVersion(u16),
+    UnexpectedEnd,
+    TrailingData,
+    InvalidOpcode(u8),
+    InvalidTag(u8),
+    InvalidString,
+    /// An operand refers to a constant, name, slot or jump target that does not exist.
+    InvalidOperand,
+    MissingReturn,
+    /// An instruction can run with fewer values or iterators than it takes, or
+    /// two paths reach it with different stack heights.
+    UnbalancedStack,
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid bytecode file at byte {}: ", self.offset)?;
+
+        match &self.kind {
+            ObjectErrorKind::BadMagic => write!(f, "not a `.mryc` file"),
+            ObjectErrorKind::UnsupportedVersion(version) => {
+                write!(f, "format version {version} is not supported, expected {VERSION}")
+            }
+            ObjectErrorKind::UnexpectedEnd => write!(f, "unexpected end of file"),
+            ObjectErrorKind::TrailingData => write!(f, "unexpected data after the program"),
+            ObjectErrorKind::InvalidOpcode(opcode) => write!(f, "unknown opcode {opcode:#04x}"),
+            ObjectErrorKind::InvalidTag(tag) => write!(f, "unknown tag {tag:#04x}"),
+            ObjectErrorKind::InvalidString => write!(f, "string is not valid UTF-8"),
+            ObjectErrorKind::InvalidOperand => write!(f, "operand out of range"),
+            ObjectErrorKind::MissingReturn => write!(f, "function does not end with `return`"),
+            ObjectErrorKind::UnbalancedStack => write!(f, "instruction does not fit the stack"),
+        }
+    }
+}
+
+impl std::error::Error for ObjectError {}
+
+/// Serializes a compiled program.
+pub fn write(program: &Prototype) -> Vec<u8> {
+    let mut writer = Writer { bytes: MAGIC.to_vec() };
+
+    writer.bytes.extend(VERSION.to_le_bytes());
+    writer.prototype(program);
+
+    writer.bytes
+}
+
+/// Deserializes a compiled program, checking that every operand is in range and
+/// that no instruction can take values or iterators that are not there.
+pub fn read(bytes: &[u8]) -> Result<Prototype, ObjectError> {
+    let mut reader = Reader { bytes, offset: 0 };
+
+    if reader.take(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        return Err(ObjectError {
+            offset: 0,
+            kind: ObjectErrorKind::BadMagic,
+        });
+    }
+
+    let version = u16::from_le_bytes([reader.u8()?, reader.u8()?]);
+
+    if version != VERSION {
+        return Err(reader.error(ObjectErrorKind::UnsupportedVersion(version)));
+    }
+
+    let program = reader.prototype(0, 0)?;
+
+    if reader.offset != bytes.len() {
+        return Err(reader.error(ObjectErrorKind::TrailingData));
+    }
+
+    Ok(program)
+}
+
+/// The opcode of an instruction and its operand, if it has one.
+fn encode(instruction: Instruction) -> (u8, Option<usize>) {
+    match instruction {
+        Instruction::Constant(index) => (0x00, Some(index)),
+        Instruction::Nil => (0x01, None),
+        Instruction::True => (0x02, None),
+        Instruction::False => (0x03, None),
+        Instruction::Pop => (0x04, None),
+        Instruction::GetLocal(slot) => (0x10, Some(slot)),
+        Instruction::SetLocal(slot) => (0x11, Some(slot)),
+        Instruction::DefineLocal(slot) => (0x12, Some(slot)),
+        Instruction::GetCell(slot) => (0x13, Some(slot)),
+        Instruction::SetCell(slot) => (0x14, Some(slot)),
+        Instruction::DefineCell(slot) => (0x15, Some(slot)),
+        Instruction::GetUpvalue(index) => (0x16, Some(index)),
+        Instruction::SetUpvalue(index) => (0x17, Some(index)),
+        Instruction::GetGlobal(index) => (0x18, Some(index)),
+        Instruction::SetGlobal(index) => (0x19, Some(index)),
+        Instruction::DefineGlobal(index) => (0x1a, Some(index)),
+        Instruction::GetAttribute(index) => (0x1b, Some(index)),
+        Instruction::SetAttribute(index) => (0x1c, Some(index)),
+        Instruction::Arithmetic(operation) => {
+            let index = OPERATIONS.iter().position(|a| *a == operation);
+
+            (0x20, index)
+        }
+        Instruction::Negate => (0x21, None),
+        Instruction::Not => (0x22, None),
+        Instruction::ToBool => (0x23, None),
+        Instruction::Equal => (0x24, None),
+        Instruction::NotEqual => (0x25, None),
+        Instruction::Less => (0x26, None),
+        Instruction::Greater => (0x27, None),
+        Instruction::LessEqual => (0x28, None),
+        Instruction::GreaterEqual => (0x29, None),
+        Instruction::Range => (0x2a, None),
+        Instruction::Array(count) => (0x2b, Some(count)),
+        Instruction::Concat(count) => (0x2c, Some(count)),
+        Instruction::Jump(target) => (0x30, Some(target)),
+        Instruction::JumpIfFalse(target) => (0x31, Some(target)),
+        Instruction::JumpIfTrue(target) => (0x32, Some(target)),
+        Instruction::IterStart => (0x33, None),
+        Instruction::IterNext(target) => (0x34, Some(target)),
+        Instruction::IterEnd => (0x35, None),
+        Instruction::Closure(index) => (0x40, Some(index)),
+        Instruction::Call(count) => (0x41, Some(count)),
+        Instruction::Return => (0x42, None),
+    }
+}
+
+fn takes_operand(opcode: u8) -> bool {
+    !matches!(
+        opcode,
+        0x01..=0x04 | 0x21..=0x2a | 0x33 | 0x35 | 0x42
+    )
+}
+
+fn decode(opcode: u8, operand: usize) -> Option<Instruction> {
+    let instruction = match opcode {
+        0x00 => Instruction::Constant(operand),
+        0x01 => Instruction::Nil,
+        0x02 => Instruction::True,
+        0x03 => Instruction::False,
+        0x04 => Instruction::Pop,
+        0x10 => Instruction::GetLocal(operand),
+        0x11 => Instruction::SetLocal(operand),
+        0x12 => Instruction::DefineLocal(operand),
+        0x13 => Instruction::GetCell(operand),
+        0x14 => Instruction::SetCell(operand),
+        0x15 => Instruction::DefineCell(operand),
+        0x16 => Instruction::GetUpvalue(operand),
+        0x17 => Instruction::SetUpvalue(operand),
+        0x18 => Instruction::GetGlobal(operand),
+        0x19 => Instruction::SetGlobal(operand),
+        0x1a => Instruction::DefineGlobal(operand),
+        0x1b => Instruction::GetAttribute(operand),
+        0x1c => Instruction::SetAttribute(operand),
+        0x20 => Instruction::Arithmetic(*OPERATIONS.get(operand)?),
+        0x21 => Instruction::Negate,
+        0x22 => Instruction::Not,
+        0x23 => Instruction::ToBool,
+        0x24 => Instruction::Equal,
+        0x25 => Instruction::NotEqual,
+        0x26 => Instruction::Less,
+        0x27 => Instruction::Greater,
+        0x28 => Instruction::LessEqual,
+        0x29 => Instruction::GreaterEqual,
+        0x2a => Instruction::Range,
+        0x2b => Instruction::Array(operand),
+        0x2c => Instruction::Concat(operand),
+        0x30 => Instruction::Jump(operand),
+        0x31 => Instruction::JumpIfFalse(operand),
+        0x32 => Instruction::JumpIfTrue(operand),
+        0x33 => Instruction::IterStart,
+        0x34 => Instruction::IterNext(operand),
+        0x35 => Instruction::IterEnd,
+        0x40 => Instruction::Closure(operand),
+        0x41 => Instruction::Call(operand),
+        0x42 => Instruction::Return,
+        _ => return None,
+    };
+
+    Some(instruction)
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: usize) {
+        let value = u32::try_from(value).expect("program too large for the object format");
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len());
+        self.bytes.extend(value.as_bytes());
+    }
+
+    fn position(&mut self, position: Position) {
+        self.u32(position.offset);
+        self.u32(position.line);
+        self.u32(position.column);
+    }
+
+    fn prototype(&mut self, prototype: &Prototype) {
+        self.string(&prototype.name);
+        self.u32(prototype.arity);
+        self.u32(prototype.slots);
+
+        for captured in &prototype.captured_parameters {
+            self.u8(*captured as u8);
+        }
+
+        self.u32(prototype.captures.len());
+
+        for capture in &prototype.captures {
+            match capture {
+                Capture::Local(slot) => {
+                    self.u8(0);
+                    self.u32(*slot);
+                }
+                Capture::Upvalue(index) => {
+                    self.u8(1);
+                    self.u32(*index);
+                }
+            }
+        }
+
+        self.chunk(&prototype.chunk);
+    }
+
+    fn chunk(&mut self, chunk: &Chunk) {
+        self.u32(chunk.constants.len());
+
+        for constant in &chunk.constants {
+            match constant {
+                Value::Number(Number::Int(int)) => {
+                    self.u8(0);
+                    self.bytes.extend(int.to_le_bytes());
+                }
+                Value::Number(Number::BigInt(int)) => {
+                    self.u8(1);
+                    self.string(&int.to_string());
+                }
+                Value::Number(Number::Float(float)) => {
+                    self.u8(2);
+                    self.bytes.extend(float.to_le_bytes());
+                }
+                Value::String(string) => {
+                    self.u8(3);
+                    self.string(string);
+                }
+                other => unreachable!("the compiler never emits {} constants", other.type_name()),
+            }
+        }
+
+        self.u32(chunk.names.len());
+
+        for name in &chunk.names {
+            self.string(name);
+        }
+
+        self.u32(chunk.functions.len());
+
+        for function in &chunk.functions {
+            self.prototype(function);
+        }
+
+        self.u32(chunk.code.len());
+
+        for instruction in &chunk.code {
+            let (opcode, operand) = encode(*instruction);
+            self.u8(opcode);
+
+            if let Some(operand) = operand {
+                self.u32(operand);
+            }
+        }
+
+        for span in &chunk.spans {
+            self.position(span.start);
+            self.position(span.end);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, kind: ObjectErrorKind) -> ObjectError {
+        ObjectError {
+            offset: self.offset,
+            kind,
+        }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&[u8], ObjectError> {
+        let Some(bytes) = self.bytes.get(self.offset..self.offset + count) else {
+            return Err(self.error(ObjectErrorKind::UnexpectedEnd));
+        };
+
+        self.offset += count;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ObjectError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, ObjectError> {
+        let bytes = self.take(4)?;
+
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    }
+
+    fn u64(&mut self) -> Result<[u8; 8], ObjectError> {
+        Ok(self.take(8)?.try_into().unwrap())
+    }
+
+    fn string(&mut self) -> Result<String, ObjectError> {
+        let length = self.u32()?;
+        let start = self.offset;
+        let bytes = self.take(length)?.to_vec();
+
+        String::from_utf8(bytes).map_err(|_| ObjectError {
+            offset: start,
+            kind: ObjectErrorKind::InvalidString,
+        })
+    }
+
+    fn position(&mut self) -> Result<Position, ObjectError> {
+        Ok(Position {
+            offset: self.u32()?,
+            line: self.u32()?,
+            column: self.u32()?,
+        })
+    }
+
+    /// Reads an operand, failing with `InvalidOperand` unless it is below `limit`.
+    fn index(&mut self, limit: usize) -> Result<usize, ObjectError> {
+        let index = self.u32()?;
+
+        if index >= limit {
+            return Err(self.error(ObjectErrorKind::InvalidOperand));
+        }
+
+        Ok(index)
+    }
+
+    /// `enclosing_slots` and `enclosing_captures` bound the captures of this prototype.
+    fn prototype(&mut self, enclosing_slots: usize, enclosing_captures: usize) -> Result<Prototype, ObjectError> {
+        let name = self.string()?;
+        let arity = self.u32()?;
+        let slots_offset = self.offset;
+        let slots = self.u32()?;
+
+        if arity > slots {
+            return Err(self.error(ObjectErrorKind::InvalidOperand));
+        }
+
+        let mut captured_parameters = Vec::new();
+
+        for _ in 0..arity {
+            captured_parameters.push(self.u8()? != 0);
+        }
+
+        let mut captures = Vec::new();
+
+        for _ in 0..self.u32()? {
+            let capture = match self.u8()? {
+                0 => Capture::Local(self.index(enclosing_slots)?),
+                1 => Capture::Upvalue(self.index(enclosing_captures)?),
+                tag => return Err(self.error(ObjectErrorKind::InvalidTag(tag))),
+            };
+
+            captures.push(capture);
+        }
+
+        let chunk = self.chunk(slots, captures.len())?;
+
+        // Every local besides the parameters is created by a define instruction, which
+        // keeps a corrupted slot count from making each call reserve a huge frame.
+        let defines = chunk
+            .code
+            .iter()
+            .filter(|a| matches!(a, Instruction::DefineLocal(_) | Instruction::DefineCell(_)))
+            .count();
+
+        if slots > arity + defines {
+            return Err(ObjectError {
+                offset: slots_offset,
+                kind: ObjectErrorKind::InvalidOperand,
+            });
+        }
+
+        Ok(Prototype {
+            name,
+            arity,
+            slots,
+            captured_parameters,
+            captures,
+            chunk,
+        })
+    }
+
+    fn chunk(&mut self, slots: usize, upvalues: usize) -> Result<Chunk, ObjectError> {
+        let mut chunk = Chunk::default();
+
+        for _ in 0..self.u32()? {
+            let constant = match self.u8()? {
+                0 => Number::Int(i64::from_le_bytes(self.u64()?)),
+                1 => {
+                    let start = self.offset;
+                    let digits = self.string()?;
+
+                    Number::BigInt(BigInt::parse(&digits).ok_or(ObjectError {
+                        offset: start,
+                        kind: ObjectErrorKind::InvalidString,
+                    })?)
+                }
+                2 => Number::Float(f64::from_le_bytes(self.u64()?)),
+                3 => {
+                    chunk.constants.push(Value::String(self.string()?));
+                    continue;
+                }
+                tag => return Err(self.error(ObjectErrorKind::InvalidTag(tag))),
+            };
+
+            chunk.constants.push(Value::Number(constant));
+        }
+
+        for _ in 0..self.u32()? {
+            chunk.names.push(self.string()?);
+        }
+
+        for _ in 0..self.u32()? {
+            chunk.functions.push(Rc::new(self.prototype(slots, upvalues)?));
+        }
+
+        let length = self.u32()?;
+        let mut offsets = Vec::new();
+
+        for _ in 0..length {
+            offsets.push(self.offset);
+
+            let opcode = self.u8()?;
+            let operand = if takes_operand(opcode) { self.u32()? } else { 0 };
+
+            let Some(instruction) = decode(opcode, operand) else {
+                return Err(self.error(ObjectErrorKind::InvalidOpcode(opcode)));
+            };
+
+            let valid = match instruction {
+                Instruction::Constant(index) => index < chunk.constants.len(),
+                Instruction::GetLocal(slot)
+                | Instruction::SetLocal(slot)
+                | Instruction::DefineLocal(slot)
+                | Instruction::GetCell(slot)
+                | Instruction::SetCell(slot)
+                | Instruction::DefineCell(slot) => slot < slots,
+                Instruction::GetUpvalue(index) | Instruction::SetUpvalue(index) => index < upvalues,
+                Instruction::GetGlobal(index)
+                | Instruction::SetGlobal(index)
+                | Instruction::DefineGlobal(index)
+                | Instruction::GetAttribute(index)
+                | Instruction::SetAttribute(index) => index < chunk.names.len(),
+                Instruction::Jump(target)
+                | Instruction::JumpIfFalse(target)
+                | Instruction::JumpIfTrue(target)
+                | Instruction::IterNext(target) => target < length,
+                Instruction::Closure(index) => index < chunk.functions.len(),
+                _ => true,
+            };
+
+            if !valid {
+                return Err(self.error(ObjectErrorKind::InvalidOperand));
+            }
+
+            chunk.code.push(instruction);
+        }
+
+        // Execution must not be able to run past the end of the code.
+        if chunk.code.last() != Some(&Instruction::Return) {
+            return Err(self.error(ObjectErrorKind::MissingReturn));
+        }
+
+        if let Err(index) = verify(&chunk.code) {
+            return Err(ObjectError {
+                offset: offsets[index],
+                kind: ObjectErrorKind::UnbalancedStack,
+            });
+        }
+
+        for _ in 0..length {
+            let start = self.position()?;
+            let end = self.position()?;
+
+            chunk.spans.push(Span::new(start, end));
+        }
+
+        Ok(chunk)
+    }
+}
+
+/// How many values an instruction pops and pushes.
+fn stack_effect(instruction: Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::Constant(_)
+        | Instruction::Nil
+        | Instruction::True
+        | Instruction::False
+        | Instruction::GetLocal(_)
+        | Instruction::GetCell(_)
+        | Instruction::GetUpvalue(_)
+        | Instruction::GetGlobal(_)
+        | Instruction::Closure(_) => (0, 1),
+        Instruction::Pop
+        | Instruction::SetLocal(_)
+        | Instruction::DefineLocal(_)
+        | Instruction::SetCell(_)
+        | Instruction::DefineCell(_)
+        | Instruction::SetUpvalue(_)
+        | Instruction::SetGlobal(_)
+        | Instruction::DefineGlobal(_)
+        | Instruction::JumpIfFalse(_)
+        | Instruction::JumpIfTrue(_)
+        | Instruction::IterStart
+        | Instruction::Return => (1, 0),
+        Instruction::GetAttribute(_)
+        | Instruction::Negate
+        | Instruction::Not
+        | Instruction::ToBool => (1, 1),
+        Instruction::SetAttribute(_) => (2, 0),
+        Instruction::Arithmetic(_)
+        | Instruction::Equal
+        | Instruction::NotEqual
+        | Instruction::Less
+        | Instruction::Greater
+        | Instruction::LessEqual
+        | Instruction::GreaterEqual
+        | Instruction::Range => (2, 1),
+        Instruction::Array(count) | Instruction::Concat(count) => (count, 1),
+        Instruction::Call(count) => (count.saturating_add(1), 1),
+        Instruction::Jump(_) | Instruction::IterNext(_) | Instruction::IterEnd => (0, 0),
+    }
+}
+
+/// Follows every path through `code`, tracking how many values and iterators the
+/// frame holds. Fails with the index of the first instruction that would take more
+/// than there is, or that is reached with two different states.
+fn verify(code: &[Instruction]) -> Result<(), usize> {
+    // Stack height and open iterators on entry to each instruction, once reached.
+    let mut states: Vec<Option<(usize, usize)>> = vec![None; code.len()];
+    let mut pending = vec![(0, (0, 0))];
+
+    while let Some((index, state)) = pending.pop() {
+        match states[index] {
+            Some(known) if known == state => continue,
+            Some(_) => return Err(index),
+            None => states[index] = Some(state),
+        }
+
+        let instruction = code[index];
+        let (height, iterators) = state;
+        let (pops, pushes) = stack_effect(instruction);
+
+        let Some(height) = height.checked_sub(pops) else {
+            return Err(index);
+        };
+
+        let height = height + pushes;
+        let next = index + 1;
+
+        let successors = match instruction {
+            Instruction::Jump(target) => vec![(target, (height, iterators))],
+            Instruction::JumpIfFalse(target) | Instruction::JumpIfTrue(target) => {
+                vec![(next, (height, iterators)), (target, (height, iterators))]
+            }
+            Instruction::IterStart => vec![(next, (height, iterators + 1))],
+            Instruction::IterNext(target) if iterators > 0 => {
+                vec![(next, (height + 1, iterators)), (target, (height, iterators - 1))]
+            }
+            Instruction::IterEnd if iterators > 0 => vec![(next, (height, iterators - 1))],
+            Instruction::IterNext(_) | Instruction::IterEnd => return Err(index),
+            Instruction::Return => Vec::new(),
+            _ => vec![(next, (height, iterators))],
+        };
+
+        // Operands are already checked, and the last instruction is a `return`,
+        // so every successor is inside the code.
+        pending.extend(successors);
+    }
+
+    Ok(())
+}
@@ -1,40 +1,76 @@
-use std::{
-    f32::consts::E,
-    io::{Read, Seek},
-};
+use std::fmt;
 
 use crate::{
     cursor::{self, VecCursor},
-    lexer::{Lexem, LexemKind},
-    tokenizer::{TResult, TokenKind},
+    lexer::{Lexem, LexemKind, LexemPart},
+    number::Number,
+    span::Span,
+    trace::{Outcome, Tracer},
 };
 
+/// How deep the syntax tree may get. Everything that walks the tree recurses once per
+/// level, so deeper input is rejected here instead of overflowing the stack later.
+const MAX_NESTING: usize = 256;
+
+/// Keywords that start a statement, where parsing resumes after a syntax error.
+const STATEMENT_KEYWORDS: [&str; 8] = ["let", "func", "if", "while", "for", "return", "break", "continue"];
+
+// Boxed to keep the `Result` returned by every parse function small.
+pub type PResult<T> = Result<T, Box<ParseError>>;
+
 #[derive(Debug, Clone)]
-pub enum Node {
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
     Ident(String),
-    Number(f64),
+    Number(Number),
     String(String),
+    Interpolated(Vec<Part>),
     Assignment {
         name: String,
         value: Box<Node>,
+        /// The `///` comment before the `let`.
+        doc: Option<String>,
+    },
+    Reassignment {
+        name: String,
+        value: Box<Node>,
+    },
+    /// `object.attribute = value`.
+    AttributeAssignment {
+        object: Box<Node>,
+        attribute: String,
+        value: Box<Node>,
     },
     Function {
         name: String,
-        arguments: Vec<Node>,
+        parameters: Vec<String>,
         code: Box<Node>,
+        /// The `///` comment before the `func`.
+        doc: Option<String>,
     },
     Return(Box<Node>),
     Add(Box<Node>, Box<Node>),
     Subtract(Box<Node>, Box<Node>),
     Multiply(Box<Node>, Box<Node>),
     Divide(Box<Node>, Box<Node>),
+    Modulo(Box<Node>, Box<Node>),
     BinOr(Box<Node>, Box<Node>),
     BinAnd(Box<Node>, Box<Node>),
     Not(Box<Node>),
+    Negate(Box<Node>),
     Or(Box<Node>, Box<Node>),
     And(Box<Node>, Box<Node>),
     Equals(Box<Node>, Box<Node>),
     NotEquals(Box<Node>, Box<Node>),
+    Less(Box<Node>, Box<Node>),
+    Greater(Box<Node>, Box<Node>),
+    LessEquals(Box<Node>, Box<Node>),
+    GreaterEquals(Box<Node>, Box<Node>),
     Call {
         callee: Box<Node>,
         arguments: Vec<Node>,
@@ -48,567 +84,1197 @@ pub enum Node {
         alternative: Box<Node>,
         block: Box<Node>,
     },
+    While {
+        label: Option<String>,
+        condition: Box<Node>,
+        block: Box<Node>,
+    },
+    For {
+        label: Option<String>,
+        variable: String,
+        iterable: Box<Node>,
+        block: Box<Node>,
+    },
+    Break(Option<String>),
+    Continue(Option<String>),
+    Range(Box<Node>, Box<Node>),
+    Array(Vec<Node>),
     Program(Vec<Node>),
     Block(Vec<Node>),
 }
 
+/// A piece of an interpolated string.
+#[derive(Debug, Clone)]
+pub enum Part {
+    Literal(String),
+    Expression(Node),
+}
+
 impl Node {
+    pub fn new(kind: NodeKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
     pub fn ident(&self) -> Option<&String> {
-        if let Node::Ident(id) = self {
+        if let NodeKind::Ident(id) = &self.kind {
             return Some(id);
         }
 
         None
     }
+
+    /// Splits a binary operation into its operator and operands.
+    pub fn binary(&self) -> Option<(BinaryOperator, &Node, &Node)> {
+        let (operator, lhs, rhs) = match &self.kind {
+            NodeKind::Range(lhs, rhs) => (BinaryOperator::Range, lhs, rhs),
+            NodeKind::Or(lhs, rhs) => (BinaryOperator::Or, lhs, rhs),
+            NodeKind::And(lhs, rhs) => (BinaryOperator::And, lhs, rhs),
+            NodeKind::BinOr(lhs, rhs) => (BinaryOperator::BinOr, lhs, rhs),
+            NodeKind::BinAnd(lhs, rhs) => (BinaryOperator::BinAnd, lhs, rhs),
+            NodeKind::Equals(lhs, rhs) => (BinaryOperator::Equals, lhs, rhs),
+            NodeKind::NotEquals(lhs, rhs) => (BinaryOperator::NotEquals, lhs, rhs),
+            NodeKind::Less(lhs, rhs) => (BinaryOperator::Less, lhs, rhs),
+            NodeKind::Greater(lhs, rhs) => (BinaryOperator::Greater, lhs, rhs),
+            NodeKind::LessEquals(lhs, rhs) => (BinaryOperator::LessEquals, lhs, rhs),
+            NodeKind::GreaterEquals(lhs, rhs) => (BinaryOperator::GreaterEquals, lhs, rhs),
+            NodeKind::Add(lhs, rhs) => (BinaryOperator::Add, lhs, rhs),
+            NodeKind::Subtract(lhs, rhs) => (BinaryOperator::Subtract, lhs, rhs),
+            NodeKind::Multiply(lhs, rhs) => (BinaryOperator::Multiply, lhs, rhs),
+            NodeKind::Divide(lhs, rhs) => (BinaryOperator::Divide, lhs, rhs),
+            NodeKind::Modulo(lhs, rhs) => (BinaryOperator::Modulo, lhs, rhs),
+            _ => return None,
+        };
+
+        Some((operator, lhs, rhs))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Range,
+    Or,
+    And,
+    BinOr,
+    BinAnd,
+    Equals,
+    NotEquals,
+    Less,
+    Greater,
+    LessEquals,
+    GreaterEquals,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+impl BinaryOperator {
+    /// Higher binds tighter.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOperator::Range => 1,
+            BinaryOperator::Or => 2,
+            BinaryOperator::And => 3,
+            BinaryOperator::BinOr => 4,
+            BinaryOperator::BinAnd => 5,
+            BinaryOperator::Equals | BinaryOperator::NotEquals => 6,
+            BinaryOperator::Less
+            | BinaryOperator::Greater
+            | BinaryOperator::LessEquals
+            | BinaryOperator::GreaterEquals => 7,
+            BinaryOperator::Add | BinaryOperator::Subtract => 8,
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 9,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOperator::Range => "..",
+            BinaryOperator::Or => "||",
+            BinaryOperator::And => "&&",
+            BinaryOperator::BinOr => "|",
+            BinaryOperator::BinAnd => "&",
+            BinaryOperator::Equals => "==",
+            BinaryOperator::NotEquals => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessEquals => "<=",
+            BinaryOperator::GreaterEquals => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+        }
+    }
+
+    fn build(self, lhs: Node, rhs: Node) -> Node {
+        let span = lhs.span.to(rhs.span);
+        let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+
+        let kind = match self {
+            BinaryOperator::Range => NodeKind::Range(lhs, rhs),
+            BinaryOperator::Or => NodeKind::Or(lhs, rhs),
+            BinaryOperator::And => NodeKind::And(lhs, rhs),
+            BinaryOperator::BinOr => NodeKind::BinOr(lhs, rhs),
+            BinaryOperator::BinAnd => NodeKind::BinAnd(lhs, rhs),
+            BinaryOperator::Equals => NodeKind::Equals(lhs, rhs),
+            BinaryOperator::NotEquals => NodeKind::NotEquals(lhs, rhs),
+            BinaryOperator::Less => NodeKind::Less(lhs, rhs),
+            BinaryOperator::Greater => NodeKind::Greater(lhs, rhs),
+            BinaryOperator::LessEquals => NodeKind::LessEquals(lhs, rhs),
+            BinaryOperator::GreaterEquals => NodeKind::GreaterEquals(lhs, rhs),
+            BinaryOperator::Add => NodeKind::Add(lhs, rhs),
+            BinaryOperator::Subtract => NodeKind::Subtract(lhs, rhs),
+            BinaryOperator::Multiply => NodeKind::Multiply(lhs, rhs),
+            BinaryOperator::Divide => NodeKind::Divide(lhs, rhs),
+            BinaryOperator::Modulo => NodeKind::Modulo(lhs, rhs),
+        };
+
+        Node::new(kind, span)
+    }
+}
+
+/// What the parser was looking for when it gave up.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    Token(LexemKind),
+    Ident,
+    Expression,
+    Block,
+    Statement,
+    Loop,
+    /// The syntax tree would get deeper than `MAX_NESTING`.
+    Shallower,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Token(kind) => write!(f, "`{kind}`"),
+            Expected::Ident => write!(f, "identifier"),
+            Expected::Expression => write!(f, "expression"),
+            Expected::Block => write!(f, "block"),
+            Expected::Statement => write!(f, "statement"),
+            Expected::Loop => write!(f, "enclosing loop"),
+            Expected::Shallower => write!(f, "less nesting"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    span: Span,
+    expected: Expected,
+    /// `None` when the input ended too early.
+    found: Option<LexemKind>,
+    message: String,
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+    pub fn expected(&self) -> &Expected {
+        &self.expected
+    }
+    pub fn found(&self) -> Option<&LexemKind> {
+        self.found.as_ref()
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: expected {}, found ", self.message, self.expected)?;
+
+        match &self.found {
+            Some(kind) => write!(f, "`{kind}`"),
+            None => write!(f, "end of input"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Splits `a.b.c` into the object `a.b` and the attribute `c`. Attribute chains
+/// nest to the right, so the last attribute is found at the bottom of the chain.
+fn split_attribute(node: Node) -> Option<(Node, String)> {
+    let NodeKind::AttributeResolve { parent, value } = node.kind else {
+        return None;
+    };
+
+    match value.kind {
+        NodeKind::Ident(attribute) => Some((*parent, attribute)),
+        NodeKind::AttributeResolve { .. } => {
+            let (rest, attribute) = split_attribute(*value)?;
+            let span = parent.span.to(rest.span);
+            let object = NodeKind::AttributeResolve {
+                parent,
+                value: Box::new(rest),
+            };
+
+            Some((Node::new(object, span), attribute))
+        }
+        _ => None,
+    }
 }
 
 pub struct Parser {
     input: cursor::VecCursor<Lexem>,
+    /// Labels of the loops enclosing the current position, innermost last.
+    loops: Vec<Option<String>>,
+    tracer: Option<Box<dyn Tracer>>,
+    /// Errors of statements that were skipped to carry on parsing.
+    errors: Vec<ParseError>,
+    /// How deep the node being parsed sits in the syntax tree.
+    depth: usize,
 }
 
 impl Parser {
     pub fn new(input: Vec<Lexem>) -> Self {
         Self {
             input: VecCursor::new(input),
+            loops: Vec::new(),
+            tracer: None,
+            errors: Vec::new(),
+            depth: 0,
         }
     }
 
-    pub fn parse_ident(&mut self) -> Option<Node> {
-        let nx = self.input.next();
+    /// Reports every grammar rule the parser tries to `tracer`.
+    pub fn with_tracer(mut self, tracer: Box<dyn Tracer>) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
 
-        match nx {
-            None => {
-                self.input.prev();
-                None
-            }
-            Some(x) => {
-                let ret = x.ident().map(|a| Node::Ident(a.clone()));
+    /// Runs `parse` as the grammar rule `rule`, telling the tracer, if any, how it went.
+    fn traced(
+        &mut self,
+        rule: &'static str,
+        parse: impl FnOnce(&mut Self) -> PResult<Option<Node>>,
+    ) -> PResult<Option<Node>> {
+        if self.tracer.is_none() {
+            return parse(self);
+        }
 
-                if ret.is_none() {
-                    self.input.prev();
-                }
+        let current = self.input.current().cloned();
 
-                ret
-            }
+        if let Some(tracer) = &mut self.tracer {
+            tracer.enter(rule, current.as_ref());
         }
+
+        let result = parse(self);
+
+        let outcome = match &result {
+            Ok(Some(_)) => Outcome::Matched,
+            Ok(None) => Outcome::Skipped,
+            Err(_) => Outcome::Failed,
+        };
+
+        if let Some(tracer) = &mut self.tracer {
+            tracer.exit(rule, outcome);
+        }
+
+        result
+    }
+
+    /// Goes one level deeper into the syntax tree, failing past `MAX_NESTING`.
+    fn descend(&mut self) -> PResult<()> {
+        if self.depth == MAX_NESTING {
+            return Err(self.error(Expected::Shallower, "nesting too deep"));
+        }
+
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    /// Runs `parse` one level deeper into the syntax tree.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> PResult<T>) -> PResult<T> {
+        self.descend()?;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    pub fn parse_ident(&mut self) -> Option<Node> {
+        let lexem = self.input.current().filter(|x| x.is_ident())?;
+        let ident = Node::new(NodeKind::Ident(lexem.ident()?.clone()), lexem.span());
+
+        self.input.next();
+
+        Some(ident)
     }
 
     pub fn parse_string(&mut self) -> Option<Node> {
-        let nx = self.input.next();
+        let nx = self.input.current()?;
 
-        println!("String: {nx:?}");
+        if let LexemKind::StringLiteral(sl) = nx.token() {
+            let string = Node::new(NodeKind::String(sl.clone()), nx.span());
+            self.input.next();
 
-        match nx {
-            None => {
-                self.input.prev();
-                None
-            }
-            Some(x) => {
-                if let LexemKind::StringLiteral(sl) = x.token() {
-                    Some(Node::String(sl.clone()))
-                } else {
-                    self.input.prev();
-                    None
-                }
-            }
+            Some(string)
+        } else {
+            None
         }
     }
 
-    pub fn parse_number(&mut self) -> Option<Node> {
-        let nx = self.input.next();
+    pub fn parse_interpolated(&mut self) -> PResult<Option<Node>> {
+        let Some(lexem) = self.input.current().cloned() else {
+            return Ok(None);
+        };
 
-        println!("Number: {nx:?}");
+        let LexemKind::Interpolated(lexem_parts) = lexem.token() else {
+            return Ok(None);
+        };
 
-        match nx {
-            None => {
-                self.input.prev();
-                None
-            }
-            Some(x) => {
-                if let LexemKind::Number(nr) = x.token() {
-                    Some(Node::Number(*nr))
-                } else {
-                    self.input.prev();
-                    None
+        self.input.next();
+
+        let mut parts = Vec::new();
+
+        for part in lexem_parts {
+            match part {
+                LexemPart::Literal(literal) => parts.push(Part::Literal(literal.clone())),
+                LexemPart::Hole(lexems) => {
+                    let mut parser = Parser::new(lexems.clone());
+                    parser.tracer = self.tracer.take();
+                    parser.depth = self.depth;
+
+                    let expression = parser.parse_expression();
+                    self.tracer = parser.tracer.take();
+
+                    let Some(expression) = expression? else {
+                        return Err(self.error_at(
+                            &lexem,
+                            Expected::Expression,
+                            "empty interpolation hole",
+                        ));
+                    };
+
+                    if !parser.input.reached_end() {
+                        return Err(parser.error(
+                            Expected::Token(LexemKind::RBrace),
+                            "unexpected token in interpolation hole",
+                        ));
+                    }
+
+                    parts.push(Part::Expression(expression));
                 }
             }
         }
+
+        Ok(Some(Node::new(NodeKind::Interpolated(parts), lexem.span())))
     }
 
-    pub fn parse_block(&mut self) -> Option<Node> {
-        let current_position = self.input.position();
+    pub fn parse_number(&mut self) -> Option<Node> {
+        let nx = self.input.current()?;
 
-        let lbrace = self.input.next().unwrap();
+        if let LexemKind::Number(nr) = nx.token() {
+            let number = Node::new(NodeKind::Number(nr.clone()), nx.span());
+            self.input.next();
 
-        if lbrace.token() != &LexemKind::LBrace {
-            self.input.prev();
+            Some(number)
+        } else {
+            None
+        }
+    }
+
+    pub fn parse_block(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
 
-            return None;
+        if !self.check(&LexemKind::LBrace) {
+            return Ok(None);
         }
 
-        println!("LBrace passed");
+        // The `{` is only consumed once the block is known not to be too deep,
+        // so that skipping the failed statement skips the whole block.
+        let value = self.nested(|parser| {
+            parser.input.next();
+            Ok(parser.parse_statements())
+        })?;
 
-        let value = self.parse();
+        self.expect(LexemKind::RBrace, "unclosed block")?;
 
-        println!("Parsed: {:#?}", value);
+        let nodes = match value.kind {
+            NodeKind::Program(nodes) => nodes,
+            _ => vec![value],
+        };
+
+        Ok(Some(self.node(NodeKind::Block(nodes), start)))
+    }
 
-        let rbrace = self.input.next().unwrap();
+    pub fn parse_comma_separated(&mut self) -> PResult<Vec<Node>> {
+        let mut values: Vec<Node> = Vec::new();
 
-        if rbrace.token() != &LexemKind::RBrace {
-            self.input.set_position(current_position);
+        while let Some(value) = self.parse_expression()? {
+            values.push(value);
 
-            return None;
+            if !self.eat(&LexemKind::Comma) {
+                break;
+            }
         }
 
-        Some(value)
+        Ok(values)
     }
 
-    pub fn parse_comma_separated(&mut self) -> Option<Vec<Node>> {
-        let mut values: Vec<Node> = Vec::new();
+    pub fn parse_function(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
+
+        if !self.check_ident("func") {
+            return Ok(None);
+        }
+
+        let doc = self.doc();
+        self.input.next();
+
+        let name = self.expect_ident("expected function name after `func`")?;
+
+        self.expect(LexemKind::LParen, "expected parameter list")?;
+
+        let parameters = self.parse_parameters()?;
+        self.expect(LexemKind::RParen, "unclosed parameter list")?;
 
-        loop {
-            let value = self.parse_expression();
+        // `break` and `continue` cannot cross a function boundary.
+        let loops = std::mem::take(&mut self.loops);
+        let block = self.parse_block();
+        self.loops = loops;
 
-            values.push(match value {
-                Some(val) => val,
-                None => break,
-            });
+        let Some(block) = block? else {
+            return Err(self.error(Expected::Block, "expected function body"));
+        };
 
-            let comma = self.input.next().unwrap();
+        let function = NodeKind::Function {
+            name,
+            parameters,
+            code: Box::new(block),
+            doc,
+        };
 
-            if comma.token() != &LexemKind::Comma {
-                self.input.prev();
+        Ok(Some(self.node(function, start)))
+    }
+
+    /// Parameter names separated by commas, possibly none and with a trailing
+    /// comma allowed, like the arguments of a call.
+    fn parse_parameters(&mut self) -> PResult<Vec<String>> {
+        let mut parameters = Vec::new();
+
+        while !self.check(&LexemKind::RParen) {
+            parameters.push(self.expect_ident("expected parameter name")?);
+
+            if !self.eat(&LexemKind::Comma) {
                 break;
             }
         }
 
-        Some(values)
+        Ok(parameters)
     }
 
-    pub fn parse_function(&mut self) -> Option<Node> {
+    pub fn parse_call(&mut self) -> PResult<Option<Node>> {
         let initial_position = self.input.position();
-        let token = self.input.next().unwrap();
 
-        if !token.is_ident_equals("func") {
-            self.input.set_position(initial_position);
+        let Some(name) = self.parse_ident() else {
+            return Ok(None);
+        };
 
-            return None;
+        if !self.eat(&LexemKind::LParen) {
+            self.input.set_position(initial_position);
+            return Ok(None);
         }
 
-        let name = self.input.next().unwrap().ident();
+        let parameters = self.parse_comma_separated()?;
+        self.expect(LexemKind::RParen, "unclosed argument list")?;
 
-        let name = match name {
-            Some(value) => value.clone(),
-            None => todo!("Implement Syntax error: expected function name!"),
+        let start = name.span;
+        let call = NodeKind::Call {
+            callee: Box::new(name),
+            arguments: parameters,
         };
 
-        println!("Parsing func with name: {name:?}");
+        Ok(Some(self.node(call, start)))
+    }
 
-        // If there's no `(`
-        if self
-            .input
-            .next()
-            .map(|a| a.token() != &LexemKind::LParen)
-            .unwrap_or(false)
-        {
-            todo!("Implement Syntax error: expected `(`!");
-        }
+    pub fn parse_primary(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
 
-        let arguments = self.parse_comma_separated();
-        println!("Args: {arguments:?}");
+        if self.eat(&LexemKind::LBracket) {
+            let elements = self.parse_comma_separated()?;
 
-        // If there's no `)`
-        if self
-            .input
-            .next()
-            .map(|a| a.token() != &LexemKind::RParen)
-            .unwrap_or(false)
-        {
-            todo!(
-                "Implement Syntax error: expected `)`! Got: {:?}",
-                self.input.current()
-            );
+            self.expect(LexemKind::RBracket, "unclosed array literal")?;
+
+            return Ok(Some(self.node(NodeKind::Array(elements), start)));
         }
 
-        let block = self.parse_block();
-        println!("Name: {name:?}; Arguments: {arguments:?}; Code: {block:#?}");
+        if !self.eat(&LexemKind::LParen) {
+            return self.parse_chained();
+        }
 
-        Some(Node::Function {
-            name,
-            arguments: arguments.unwrap(),
-            code: {
-                let Some(x) = block else {
-                    panic!("Expected block, but got None.");
-                };
+        let Some(expression) = self.parse_expression()? else {
+            return Err(self.error(Expected::Expression, "expected expression after `(`"));
+        };
 
-                Box::new(x)
-            },
-        })
+        self.expect(LexemKind::RParen, "unclosed parenthesis")?;
+
+        Ok(Some(self.node(expression.kind, start)))
     }
 
-    pub fn parse_call(&mut self) -> Option<Node> {
-        let initial_position = self.input.position();
-        let name = self.parse_ident();
+    pub fn parse_unary(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
+        let operator = if self.eat(&LexemKind::Exclamation) {
+            "!"
+        } else if self.eat(&LexemKind::Minus) {
+            "-"
+        } else {
+            return self.parse_primary();
+        };
 
-        let name = match name {
-            Some(value) => value,
-            None => {
-                self.input.set_position(initial_position);
-                return None;
-            }
+        let Some(operand) = self.nested(Self::parse_unary)? else {
+            return Err(self.error(
+                Expected::Expression,
+                format!("expected expression after `{operator}`"),
+            ));
         };
 
-        println!("call: {name:?}");
+        let kind = match operator {
+            "!" => NodeKind::Not(Box::new(operand)),
+            _ => NodeKind::Negate(Box::new(operand)),
+        };
 
-        // If there's no `(`
-        if self
-            .input
-            .next()
-            .map(|a| a.token() != &LexemKind::LParen)
-            .unwrap_or(false)
-        {
-            self.input.set_position(initial_position);
-            return None;
-        }
+        Ok(Some(self.node(kind, start)))
+    }
 
-        let parameters = self.parse_comma_separated();
-        println!("call: parameters: {parameters:?}");
+    /// Precedence climbing: only operators binding at least as tight as
+    /// `min_precedence` are consumed, so every level is left-associative.
+    fn parse_binary(&mut self, min_precedence: u8) -> PResult<Option<Node>> {
+        let Some(mut lhs) = self.parse_unary()? else {
+            return Ok(None);
+        };
 
-        // If there's no `)`
-        if self
-            .input
-            .next()
-            .map(|a| a.token() != &LexemKind::RParen)
-            .unwrap_or(false)
-        {
-            todo!(
-                "Implement Syntax error: expected `)`! ({:?}",
-                self.input.current()
-            );
+        let depth = self.depth;
+
+        while let Some(operator) = self.peek_operator() {
+            let precedence = operator.precedence();
+
+            if precedence < min_precedence {
+                break;
+            }
+
+            self.input.next();
+
+            // Chains nest to the left, so `1 + 2 + 3` is as deep as it is long.
+            self.descend()?;
+
+            let Some(rhs) = self.parse_binary(precedence + 1)? else {
+                return Err(self.error(
+                    Expected::Expression,
+                    format!("expected expression after `{}`", operator.symbol()),
+                ));
+            };
+
+            lhs = operator.build(lhs, rhs);
         }
 
-        Some(Node::Call {
-            callee: Box::new(name),
-            arguments: parameters.unwrap(),
-        })
+        self.depth = depth;
+
+        Ok(Some(lhs))
     }
 
-    pub fn parse_expression_level_1(&mut self) -> Option<Node> {
-        let current_node = self.parse_chained();
+    pub fn parse_expression(&mut self) -> PResult<Option<Node>> {
+        self.nested(|parser| parser.parse_binary(0))
+    }
 
-        eprintln!("Current node is: {:?}", current_node);
+    /// Recognises the binary operator at the cursor.
+    fn peek_operator(&self) -> Option<BinaryOperator> {
+        let operator = match self.input.current()?.token() {
+            LexemKind::DoubleDot => BinaryOperator::Range,
+            LexemKind::DoubleOr => BinaryOperator::Or,
+            LexemKind::DoubleAnd => BinaryOperator::And,
+            LexemKind::DoubleEquals => BinaryOperator::Equals,
+            LexemKind::NotEquals => BinaryOperator::NotEquals,
+            LexemKind::LessEquals => BinaryOperator::LessEquals,
+            LexemKind::GreaterEquals => BinaryOperator::GreaterEquals,
+            LexemKind::Or => BinaryOperator::BinOr,
+            LexemKind::And => BinaryOperator::BinAnd,
+            LexemKind::Less => BinaryOperator::Less,
+            LexemKind::Greater => BinaryOperator::Greater,
+            LexemKind::Plus => BinaryOperator::Add,
+            LexemKind::Minus => BinaryOperator::Subtract,
+            LexemKind::Asterisk => BinaryOperator::Multiply,
+            LexemKind::Slash => BinaryOperator::Divide,
+            LexemKind::Percent => BinaryOperator::Modulo,
+            _ => return None,
+        };
 
-        let next_lexem = self.input.next();
+        Some(operator)
+    }
 
-        if *next_lexem.unwrap().token() == LexemKind::Equals {
-            // Should be `==`
-            let next_lexem = self.input.next();
-            if *next_lexem.unwrap().token() == LexemKind::Equals {
-                // It's `==`!
+    pub fn parse_if(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
 
-                let node = self.parse_expression_level_1();
+        if !self.check_ident("if") {
+            return Ok(None);
+        }
 
-                eprintln!("Node: {:?}", node);
+        self.input.next();
 
-                return Some(Node::Equals(
-                    Box::new(current_node.unwrap()),
-                    Box::new(node.unwrap()),
-                ));
-            }
-        } else {
-            // It seems it's a bare value
-            self.input.prev();
+        let Some(condition) = self.parse_expression()? else {
+            return Err(self.error(Expected::Expression, "expected condition after `if`"));
+        };
+
+        let Some(block) = self.parse_block()? else {
+            return Err(self.error(Expected::Block, "expected block after `if` condition"));
+        };
 
-            eprintln!("Bare value! {current_node:?}");
+        let alternative = self.parse_else()?.unwrap_or_else(|| {
+            Node::new(NodeKind::Program(Vec::new()), block.span)
+        });
 
-            // if let Some(node) = current_node {
-            //     return Some(node);
-            // } else {
-            //     todo!("Parse other value from expression: {current_node:?}");
-            // }
+        let condition = NodeKind::If {
+            condition: Box::new(condition),
+            alternative: Box::new(alternative),
+            block: Box::new(block),
+        };
+
+        Ok(Some(self.node(condition, start)))
+    }
 
-            return current_node;
+    /// Parses an optional `else { … }` or `else if …` following an `if` block.
+    pub fn parse_else(&mut self) -> PResult<Option<Node>> {
+        if !self.check_ident("else") {
+            return Ok(None);
         }
 
-        todo!("Expression!")
+        self.input.next();
+
+        if let Some(chained) = self.nested(Self::parse_if)? {
+            return Ok(Some(chained));
+        }
+
+        match self.parse_block()? {
+            Some(block) => Ok(Some(block)),
+            None => Err(self.error(Expected::Block, "expected block or `if` after `else`")),
+        }
     }
 
-    pub fn parse_expression(&mut self) -> Option<Node> {
-        let lhs = self.parse_expression_level_1();
+    /// Parses `while` and `for` loops, optionally preceded by a `label:`.
+    pub fn parse_loop(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
+
+        let is_labelled = self.input.current().map(|a| a.is_ident()).unwrap_or(false)
+            && matches!(self.input.peek(1).map(|a| a.token()), Some(LexemKind::Colon))
+            && self
+                .input
+                .peek(2)
+                .map(|a| a.is_ident_equals("while") || a.is_ident_equals("for"))
+                .unwrap_or(false);
+
+        let label = if is_labelled {
+            let label = self.parse_ident().and_then(|a| a.ident().cloned());
+            self.input.next();
+
+            label
+        } else {
+            None
+        };
+
+        if self.check_ident("while") {
+            self.input.next();
 
-        let after = self.input.next()?;
+            let Some(condition) = self.parse_expression()? else {
+                return Err(self.error(Expected::Expression, "expected condition after `while`"));
+            };
 
-        if after.token() == &LexemKind::Or {
-            let next = self.input.next()?;
+            let block = self.parse_loop_body(&label)?;
+            let kind = NodeKind::While {
+                label,
+                condition: Box::new(condition),
+                block: Box::new(block),
+            };
 
-            if next.token() == &LexemKind::Or {
-                let rhs = self.parse_expression_level_1();
+            return Ok(Some(self.node(kind, start)));
+        }
 
-                // println!("LHS: {lhs:?}");
-                // println!("RHS: {rhs:?}");
+        if self.check_ident("for") {
+            self.input.next();
 
-                // todo!("WTF");
+            let variable = self.expect_ident("expected loop variable after `for`")?;
 
-                return Some(Node::Or(Box::new(lhs.unwrap()), Box::new(rhs.unwrap())));
+            if !self.check_ident("in") {
+                return Err(self.error(
+                    Expected::Token(LexemKind::Ident("in".to_string())),
+                    "expected `in` after loop variable",
+                ));
             }
-        } else if after.token() == &LexemKind::Plus {
-            let rhs = self.parse_expression_level_1();
 
-            return Some(Node::Add(Box::new(lhs.unwrap()), Box::new(rhs.unwrap())));
-        } else if after.token() == &LexemKind::Minus {
-            let rhs = self.parse_expression_level_1();
+            self.input.next();
 
-            return Some(Node::Subtract(
-                Box::new(lhs.unwrap()),
-                Box::new(rhs.unwrap()),
-            ));
-        } else {
-            self.input.prev();
+            let Some(iterable) = self.parse_expression()? else {
+                return Err(self.error(Expected::Expression, "expected value to iterate over"));
+            };
+
+            let block = self.parse_loop_body(&label)?;
+            let kind = NodeKind::For {
+                label,
+                variable,
+                iterable: Box::new(iterable),
+                block: Box::new(block),
+            };
+
+            return Ok(Some(self.node(kind, start)));
         }
 
-        return lhs;
+        Ok(None)
     }
 
-    pub fn parse_if(&mut self) -> Option<Node> {
-        let initial_position = self.input.position();
-        let token = self.input.next().unwrap();
+    fn parse_loop_body(&mut self, label: &Option<String>) -> PResult<Node> {
+        self.loops.push(label.clone());
+        let block = self.parse_block();
+        self.loops.pop();
 
-        if !token.is_ident_equals("if") {
-            self.input.set_position(initial_position);
+        match block? {
+            Some(block) => Ok(block),
+            None => Err(self.error(Expected::Block, "expected loop body")),
+        }
+    }
+
+    /// Parses `break` and `continue`, with an optional label on the same line.
+    pub fn parse_jump(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
+        let is_break = self.check_ident("break");
 
-            return None;
+        if !is_break && !self.check_ident("continue") {
+            return Ok(None);
         }
 
-        let condition = self.parse_expression();
+        let Some(keyword) = self.input.next().cloned() else {
+            return Ok(None);
+        };
 
-        eprintln!("{condition:#?}");
+        let label = match self.input.current() {
+            Some(lexem) if lexem.is_ident() && lexem.span().line() == keyword.span().line() => {
+                self.parse_ident().and_then(|a| a.ident().cloned())
+            }
+            _ => None,
+        };
 
-        eprintln!("{:?}", self.input.current());
+        if self.loops.is_empty() {
+            return Err(self.error_at(
+                &keyword,
+                Expected::Loop,
+                format!("`{}` outside of a loop", keyword.token()),
+            ));
+        }
 
-        let block = self.parse_block();
+        if let Some(name) = &label
+            && !self.loops.contains(&label)
+            && let Some(lexem) = self.input.previous()
+        {
+            return Err(self.error_at(lexem, Expected::Loop, format!("unknown loop label `{name}`")));
+        }
 
-        eprintln!("Block: {block:?}");
+        let kind = if is_break {
+            NodeKind::Break(label)
+        } else {
+            NodeKind::Continue(label)
+        };
+        let jump = self.node(kind, start);
 
-        return Some(Node::If {
-            condition: Box::new(condition.unwrap()),
-            alternative: Box::new(Node::Program(Vec::new())),
-            block: Box::new(block.unwrap()),
-        });
+        self.consume_semicolon();
+
+        Ok(Some(jump))
     }
 
-    pub fn parse_return(&mut self) -> Option<Node> {
-        let token = self.input.next();
-        let is_return_token = token
-            .map(|a| a.ident().map(|x| x == "return").unwrap_or(false))
-            .unwrap_or(false);
+    /// Parses `name = value` for a variable that is already declared.
+    pub fn parse_reassignment(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
 
-        if !is_return_token {
-            self.input.prev();
+        let is_reassignment = self.input.current().map(|a| a.is_ident()).unwrap_or(false)
+            && matches!(self.input.peek(1).map(|a| a.token()), Some(LexemKind::Equals));
 
-            return None;
+        if !is_reassignment {
+            return Ok(None);
         }
 
-        let expression = self.parse_expression();
+        let name = self.expect_ident("expected variable name")?;
+        self.input.next();
 
-        eprintln!("Expression: {expression:#?}");
+        let Some(value) = self.parse_expression()? else {
+            return Err(self.error(Expected::Expression, "expected value after `=`"));
+        };
+
+        let kind = NodeKind::Reassignment {
+            name,
+            value: Box::new(value),
+        };
+        let reassignment = self.node(kind, start);
 
         self.consume_semicolon();
 
-        return Some(Node::Return(Box::new(expression.unwrap())));
+        Ok(Some(reassignment))
     }
 
-    pub fn parse_declaration(&mut self) -> Option<Node> {
-        let position = self.input.position();
+    /// Parses the `= value` of `object.attribute = value` after its target has been
+    /// parsed as an expression. Returns `target` unchanged if no `=` follows.
+    fn parse_attribute_assignment(&mut self, target: Node) -> PResult<Node> {
+        if !self.check(&LexemKind::Equals) {
+            return Ok(target);
+        }
+
+        let start = target.span;
 
-        let token = self.input.next().cloned();
-        let is_let_token = token
-            .as_ref()
-            .map(|a| a.ident().map(|x| x == "let").unwrap_or(false))
-            .unwrap_or(false);
+        let Some((object, attribute)) = split_attribute(target) else {
+            return Err(self.error(Expected::Statement, "cannot assign to this expression"));
+        };
 
-        if !is_let_token {
-            self.input.prev();
+        self.input.next();
 
-            return None;
-        }
+        let Some(value) = self.parse_expression()? else {
+            return Err(self.error(Expected::Expression, "expected value after `=`"));
+        };
+
+        let kind = NodeKind::AttributeAssignment {
+            object: Box::new(object),
+            attribute,
+            value: Box::new(value),
+        };
 
-        let name = self.parse_ident();
+        Ok(self.node(kind, start))
+    }
 
-        let sign = self.input.next().cloned();
-        let is_equal_sign = sign
-            .as_ref()
-            .map(|x| x.token() == &LexemKind::Equals)
-            .unwrap_or(false);
+    pub fn parse_return(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
 
-        if !is_equal_sign {
-            self.input.set_position(position);
+        if !self.check_ident("return") {
+            return Ok(None);
         }
 
-        let expression = self.parse_expression();
+        self.input.next();
 
-        eprintln!("Name: {name:?}; Sign: {sign:?}; Expression: {expression:#?}");
+        let Some(expression) = self.parse_expression()? else {
+            return Err(self.error(Expected::Expression, "expected expression after `return`"));
+        };
 
-        self.consume_semicolon();
+        let ret = self.node(NodeKind::Return(Box::new(expression)), start);
 
-        // todo!("Is assignment stable?");
+        self.consume_semicolon();
 
-        return Some(Node::Assignment {
-            name: name.map(|x| x.ident().cloned().unwrap()).unwrap(),
-            value: Box::new(expression.unwrap()),
-        });
+        Ok(Some(ret))
     }
 
-    pub fn parse_attr_resolve(&mut self) -> Option<Node> {
-        // let position = self.input.position();
+    pub fn parse_declaration(&mut self) -> PResult<Option<Node>> {
+        let start = self.span();
+
+        if !self.check_ident("let") {
+            return Ok(None);
+        }
+
+        let doc = self.doc();
+        self.input.next();
+
+        let name = self.expect_ident("expected variable name after `let`")?;
+
+        self.expect(LexemKind::Equals, "expected `=` after variable name")?;
+
+        let Some(expression) = self.parse_expression()? else {
+            return Err(self.error(Expected::Expression, "expected value after `=`"));
+        };
+
+        let assignment = NodeKind::Assignment {
+            name,
+            value: Box::new(expression),
+            doc,
+        };
+        let assignment = self.node(assignment, start);
+
+        self.consume_semicolon();
 
-        let object = self.parse_atom();
-        let token = self.input.next();
+        Ok(Some(assignment))
+    }
 
-        if !token.map(|x| x.token() == &LexemKind::Dot).unwrap_or(false) {
-            self.input.prev();
+    pub fn parse_attr_resolve(&mut self) -> PResult<Option<Node>> {
+        let Some(object) = self.parse_atom()? else {
+            return Ok(None);
+        };
 
-            return object;
+        if !self.eat(&LexemKind::Dot) {
+            return Ok(Some(object));
         }
 
-        let attr = self.parse_chained();
+        let Some(attr) = self.nested(Self::parse_chained)? else {
+            return Err(self.error(Expected::Ident, "expected attribute after `.`"));
+        };
 
-        return Some(Node::AttributeResolve {
-            parent: Box::new(object.unwrap()),
-            value: Box::new(attr.unwrap()),
-        });
+        let span = object.span.to(attr.span);
+        let resolve = NodeKind::AttributeResolve {
+            parent: Box::new(object),
+            value: Box::new(attr),
+        };
+
+        Ok(Some(Node::new(resolve, span)))
     }
 
-    pub fn parse_chained(&mut self) -> Option<Node> {
-        println!("? Attribute resolve");
-        if let Some(res) = self.parse_attr_resolve() {
-            println!("+ Attribute resolve: {:?}", &res);
-            return Some(res);
+    pub fn parse_chained(&mut self) -> PResult<Option<Node>> {
+        if let Some(res) = self.traced("attribute", Self::parse_attr_resolve)? {
+            return Ok(Some(res));
         }
 
-        return self.parse_atom();
+        self.parse_atom()
     }
 
-    pub fn parse_atom(&mut self) -> Option<Node> {
-        println!("? Call");
-        if let Some(call) = self.parse_call() {
-            println!("+ Call: {:?}", &call);
-            return Some(call);
+    pub fn parse_atom(&mut self) -> PResult<Option<Node>> {
+        if let Some(call) = self.traced("call", Self::parse_call)? {
+            return Ok(Some(call));
         }
 
-        println!("? Ident");
-        if let Some(ident) = self.parse_ident() {
-            println!("+ Ident: {:?}", &ident);
-            return Some(ident);
+        if let Some(ident) = self.traced("ident", |parser| Ok(parser.parse_ident()))? {
+            return Ok(Some(ident));
         }
 
-        println!("? String");
-        if let Some(string) = self.parse_string() {
-            println!("+ String: {:?}", &string);
-            return Some(string);
+        if let Some(string) = self.traced("string", |parser| Ok(parser.parse_string()))? {
+            return Ok(Some(string));
         }
 
-        println!("? Number");
-        if let Some(number) = self.parse_number() {
-            println!("+ Number: {:?}", &number);
-            return Some(number);
+        if let Some(string) = self.traced("interpolated string", Self::parse_interpolated)? {
+            return Ok(Some(string));
         }
 
-        return None;
+        if let Some(number) = self.traced("number", |parser| Ok(parser.parse_number()))? {
+            return Ok(Some(number));
+        }
+
+        Ok(None)
     }
 
-    pub fn parse_once(&mut self) -> Option<Node> {
-        println!("? Block");
-        if let Some(block) = self.parse_block() {
-            println!("+ Block: {:?}", &block);
-            return Some(block);
+    pub fn parse_once(&mut self) -> PResult<Option<Node>> {
+        if let Some(block) = self.traced("block", Self::parse_block)? {
+            return Ok(Some(block));
         }
 
-        println!("? Condition");
-        if let Some(condition) = self.parse_if() {
-            println!("+ Condition: {:?}", &condition);
-            return Some(condition);
+        if let Some(condition) = self.traced("if", Self::parse_if)? {
+            return Ok(Some(condition));
         }
 
-        println!("? Function");
-        if let Some(func) = self.parse_function() {
-            println!("+ Function: {:?}", &func);
-            return Some(func);
+        if let Some(func) = self.traced("function", Self::parse_function)? {
+            return Ok(Some(func));
         }
 
-        println!("? Declaration");
-        if let Some(decl) = self.parse_declaration() {
-            println!("+ Declaration: {:?}", &decl);
-            return Some(decl);
+        if let Some(decl) = self.traced("declaration", Self::parse_declaration)? {
+            return Ok(Some(decl));
         }
 
-        println!("? Return");
-        if let Some(ret) = self.parse_return() {
-            println!("+ Return: {:?}", &ret);
-            return Some(ret);
+        if let Some(lp) = self.traced("loop", Self::parse_loop)? {
+            return Ok(Some(lp));
         }
 
-        println!("? Expression");
-        if let Some(expr) = self.parse_expression() {
-            println!("+ Expression: {:?}", &expr);
-            return Some(expr);
+        if let Some(ret) = self.traced("return", Self::parse_return)? {
+            return Ok(Some(ret));
         }
 
-        println!("? Block exit");
-        if self
-            .input
-            .current()
-            .map(|x| x.token() == &LexemKind::RBrace)
-            .unwrap_or(false)
-        {
-            return None;
+        if let Some(jump) = self.traced("jump", Self::parse_jump)? {
+            return Ok(Some(jump));
         }
 
-        self.consume_semicolon();
-
-        let current_token_data = self
-            .input
-            .current()
-            .map(|a| (a.token(), a.line(), a.column()));
+        if let Some(reassignment) = self.traced("reassignment", Self::parse_reassignment)? {
+            return Ok(Some(reassignment));
+        }
 
-        if let Some(data) = current_token_data {
-            todo!(
-                "Syntax error: Token: {:?}, Line: {:?}; Column: {:?}",
-                data.0,
-                data.1,
-                data.2,
-            );
+        if let Some(expr) = self.traced("expression", Self::parse_expression)? {
+            let statement = self.parse_attribute_assignment(expr)?;
+            self.consume_semicolon();
+            return Ok(Some(statement));
         }
 
-        return None;
+        Ok(None)
     }
 
-    pub fn parse(&mut self) -> Node {
-        println!("= Entering parse");
+    /// Parses statements until the end of input or a closing `}`, which is left for the caller.
+    /// A statement that fails to parse is recorded in `errors` and skipped.
+    fn parse_statements(&mut self) -> Node {
         let mut actions: Vec<Node> = Vec::new();
 
         while !self.input.reached_end() {
-            let node = self.parse_once();
-
-            if node.is_none() {
+            if self.check(&LexemKind::RBrace) {
                 break;
             }
 
-            actions.push(node.unwrap());
-        }
+            if self.consume_semicolon() {
+                continue;
+            }
+
+            let start = self.input.position();
+            let depth = self.depth;
 
-        println!("= Exiting parse");
+            let error = match self.parse_once() {
+                Ok(Some(node)) => {
+                    actions.push(node);
+                    continue;
+                }
+                Ok(None) => self.error(Expected::Statement, "unexpected token"),
+                Err(error) => error,
+            };
+
+            // A failed statement can leave the depth of the place it failed at behind.
+            self.depth = depth;
+            self.errors.push(*error);
+            self.synchronize(start);
+        }
 
         match actions.len() {
             1 => actions.pop().unwrap(),
-            _ => Node::Program(actions),
+            _ => {
+                let span = match (actions.first(), actions.last()) {
+                    (Some(first), Some(last)) => first.span.to(last.span),
+                    _ => self.span(),
+                };
+
+                Node::new(NodeKind::Program(actions), span)
+            }
         }
     }
 
-    fn consume_semicolon(&self) -> bool {
-        let token = self.input.next();
+    /// Skips the rest of a statement that failed to parse, which started at `start`, up to
+    /// the next point where parsing can resume: after a `;`, or before a `}` or a keyword
+    /// that starts a statement. Blocks are skipped whole, so their contents do not count.
+    fn synchronize(&mut self, start: usize) {
+        // Always move past at least one lexem, or the same error would come back forever.
+        // A `{` is skipped below together with the rest of its block.
+        if self.input.position() == start && !self.check(&LexemKind::LBrace) {
+            self.input.next();
+        }
 
-        token
-            .map(|a| a.token() == &LexemKind::Semicolon)
+        let mut depth = 0usize;
+        // The `if` of an `else if` continues the statement rather than starting one.
+        let mut after_else = false;
+
+        while let Some(lexem) = self.input.current() {
+            match lexem.token() {
+                LexemKind::LBrace => depth += 1,
+                LexemKind::RBrace if depth == 0 => return,
+                LexemKind::RBrace => depth -= 1,
+                LexemKind::Semicolon if depth == 0 => {
+                    self.input.next();
+                    return;
+                }
+                _ if depth == 0 && !after_else && STATEMENT_KEYWORDS.iter().any(|a| lexem.is_ident_equals(a)) => return,
+                _ => {}
+            }
+
+            after_else = lexem.is_ident_equals("else");
+            self.input.next();
+        }
+    }
+
+    /// Parses the whole input, reporting every syntax error rather than just the first.
+    pub fn parse(&mut self) -> Result<Node, Vec<ParseError>> {
+        let program = self.parse_statements();
+
+        // A `}` without a matching `{` ends the statements early; report it and keep going.
+        while !self.input.reached_end() {
+            let error = self.error(Expected::Statement, "unmatched `}`");
+            self.errors.push(*error);
+            self.input.next();
+
+            self.parse_statements();
+        }
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+
+        Ok(program)
+    }
+
+    fn check(&self, kind: &LexemKind) -> bool {
+        self.input
+            .current()
+            .map(|a| a.token() == kind)
+            .unwrap_or(false)
+    }
+
+    fn check_ident(&self, value: &str) -> bool {
+        self.input
+            .current()
+            .map(|a| a.is_ident_equals(value))
             .unwrap_or(false)
     }
+
+    /// Consumes the current lexem if it is of `kind`.
+    fn eat(&self, kind: &LexemKind) -> bool {
+        let matches = self.check(kind);
+
+        if matches {
+            self.input.next();
+        }
+
+        matches
+    }
+
+    fn expect(&self, kind: LexemKind, message: &str) -> PResult<()> {
+        if self.eat(&kind) {
+            return Ok(());
+        }
+
+        Err(self.error(Expected::Token(kind), message))
+    }
+
+    fn expect_ident(&mut self, message: &str) -> PResult<String> {
+        match self.parse_ident() {
+            Some(Node {
+                kind: NodeKind::Ident(name),
+                ..
+            }) => Ok(name),
+            _ => Err(self.error(Expected::Ident, message)),
+        }
+    }
+
+    /// The doc comment of the current lexem.
+    fn doc(&self) -> Option<String> {
+        self.input.current()?.doc().map(String::from)
+    }
+
+    /// Span of the current lexem, or of the last one if the input has ended.
+    fn span(&self) -> Span {
+        self.input
+            .current()
+            .or(self.input.last())
+            .map(|a| a.span())
+            .unwrap_or_default()
+    }
+
+    /// Wraps `kind` in a node covering everything from `start` up to the last consumed lexem.
+    fn node(&self, kind: NodeKind, start: Span) -> Node {
+        let end = self.input.previous().map(|a| a.span()).unwrap_or(start);
+
+        Node::new(kind, start.to(end))
+    }
+
+    /// Builds an error pointing at the current lexem, or at the last one if the input has ended.
+    fn error(&self, expected: Expected, message: impl Into<String>) -> Box<ParseError> {
+        let found = self.input.current().map(|a| a.token().clone());
+
+        Box::new(ParseError {
+            span: self.span(),
+            expected,
+            found,
+            message: message.into(),
+        })
+    }
+
+    /// Builds an error pointing at an already consumed `lexem`.
+    fn error_at(&self, lexem: &Lexem, expected: Expected, message: impl Into<String>) -> Box<ParseError> {
+        Box::new(ParseError {
+            span: lexem.span(),
+            expected,
+            found: Some(lexem.token().clone()),
+            message: message.into(),
+        })
+    }
+
+    fn consume_semicolon(&self) -> bool {
+        self.eat(&LexemKind::Semicolon)
+    }
 }
@@ -0,0 +1,297 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use crate::tree;
+
+use maaray::{
+    Ast, Diagnostic, Engine, Value,
+    diagnostics::Renderer,
+    lexer::{LexError, Lexem, Lexer},
+    parser::Parser,
+    tokenizer::{Tokenizer, TokenizerErrorKind},
+};
+
+const PROMPT: &str = ">> ";
+const CONTINUATION: &str = ".. ";
+
+const HELP: &str = "\
+:ast <code>      print the syntax tree of <code>
+:tokens <code>   print the lexems of <code>
+:load <file>     run a script in the current environment
+:reset           forget every variable and function
+:help            show this message
+:quit            leave the REPL (or press Ctrl-D)";
+
+struct EntryError {
+    diagnostics: Vec<Diagnostic>,
+    /// The input stopped in the middle of something, like an unclosed `{` or string,
+    /// and may become valid once more lines are added.
+    incomplete: bool,
+}
+
+fn lex(code: &str) -> Result<Vec<Lexem>, EntryError> {
+    let (lexems, errors): (Vec<_>, Vec<_>) = Lexer::new(Tokenizer::new(code)).partition(|a| a.is_ok());
+    let errors: Vec<_> = errors.into_iter().filter_map(|a| a.err()).collect();
+
+    if !errors.is_empty() {
+        return Err(EntryError {
+            diagnostics: errors.iter().map(Diagnostic::from).collect(),
+            incomplete: errors
+                .iter()
+                .any(|a| match a {
+                    LexError::Tokenizer(e) => matches!(e.kind(), TokenizerErrorKind::UnterminatedString),
+                    LexError::UnterminatedComment(_) => true,
+                    _ => false,
+                }),
+        });
+    }
+
+    Ok(lexems.into_iter().filter_map(|a| a.ok()).collect())
+}
+
+fn parse(code: &str) -> Result<Ast, EntryError> {
+    let mut parser = Parser::new(lex(code)?);
+
+    parser.parse().map_err(|errors| EntryError {
+        diagnostics: errors.iter().map(Diagnostic::from).collect(),
+        // Errors before the end of the input will not go away by typing more.
+        incomplete: errors.iter().all(|a| a.found().is_none()),
+    })
+}
+
+/// Prints what an entry or command produced. A failure shows up again when `run`
+/// writes the next prompt, which ends the REPL.
+fn output(text: impl fmt::Display) {
+    write!(io::stdout(), "{text}").ok();
+}
+
+fn report<'a>(file_name: &str, source: &str, diagnostics: impl IntoIterator<Item = &'a Diagnostic>) {
+    let renderer = Renderer::new(file_name, source);
+
+    for diagnostic in diagnostics {
+        eprintln!("{}", renderer.render(diagnostic));
+    }
+}
+
+/// What the REPL did with a line of input.
+#[derive(Debug, PartialEq)]
+enum Line {
+    /// The entry was run, or its errors were reported.
+    Done,
+    /// The entry is unfinished and waits for more lines.
+    Continued,
+    Quit,
+}
+
+/// Reads entries from stdin and runs them one by one in a single, persistent global scope.
+pub struct Repl {
+    engine: Engine,
+    /// The lines of an unfinished entry.
+    buffer: String,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            engine: Engine::new(),
+            buffer: String::new(),
+        }
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut stdin = io::stdin().lock();
+
+        loop {
+            let mut stdout = io::stdout();
+            write!(stdout, "{}", if self.buffer.is_empty() { PROMPT } else { CONTINUATION })?;
+            stdout.flush()?;
+
+            let mut line = String::new();
+
+            if stdin.read_line(&mut line)? == 0 {
+                writeln!(stdout)?;
+
+                // The end of the input ends an unfinished entry, like an empty line.
+                if !self.buffer.is_empty() {
+                    self.line("\n");
+                }
+
+                return Ok(());
+            }
+
+            if self.line(&line) == Line::Quit {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Adds `line` to the current entry, and runs the entry once it is complete.
+    fn line(&mut self, line: &str) -> Line {
+        if self.buffer.is_empty() {
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() {
+                return Line::Done;
+            }
+
+            if let Some(command) = trimmed.strip_prefix(':') {
+                return if self.command(command) { Line::Done } else { Line::Quit };
+            }
+        }
+
+        // An empty line ends a continuation and reports whatever is still missing.
+        let force = !self.buffer.is_empty() && line.trim().is_empty();
+        self.buffer.push_str(line);
+
+        let buffer = std::mem::take(&mut self.buffer);
+
+        match parse(&buffer) {
+            Ok(program) => self.evaluate("<repl>", &buffer, &program),
+            Err(e) if e.incomplete && !force => {
+                self.buffer = buffer;
+                return Line::Continued;
+            }
+            Err(e) => report("<repl>", &buffer, &e.diagnostics),
+        }
+
+        Line::Done
+    }
+
+    /// Prints the value of the entry's last statement, unless it is `nil`.
+    fn evaluate(&mut self, file_name: &str, source: &str, program: &Ast) {
+        match self.engine.run(program) {
+            Ok(Value::Nil) => {}
+            Ok(value) => output(format_args!("{value:?}\n")),
+            Err(diagnostics) => report(file_name, source, diagnostics.iter()),
+        }
+    }
+
+    /// Runs a `:command`. Returns `false` when the REPL should exit.
+    fn command(&mut self, command: &str) -> bool {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+
+        match (name, argument) {
+            ("ast" | "tokens" | "load", "") => eprintln!("usage: :{name} <{}>", if name == "load" { "file" } else { "code" }),
+            ("ast", code) => match parse(code) {
+                Ok(program) => output(tree::node(&program)),
+                Err(e) => report("<repl>", code, &e.diagnostics),
+            },
+            ("tokens", code) => match lex(code) {
+                Ok(lexems) => {
+                    for lexem in lexems {
+                        let span = lexem.span();
+                        output(format_args!("{}:{}\t{:?}\n", span.line(), span.column(), lexem.token()));
+                    }
+                }
+                Err(e) => report("<repl>", code, &e.diagnostics),
+            },
+            ("load", file_name) => {
+                if let Err(e) = self.load(file_name) {
+                    eprintln!("cannot read `{file_name}`: {e}");
+                }
+            }
+            ("reset", _) => {
+                self.engine = Engine::new();
+                output("Environment reset.\n");
+            }
+            ("help", _) => output(format_args!("{HELP}\n")),
+            ("quit" | "q", _) => return false,
+            _ => eprintln!("unknown command `:{name}`, type `:help` for a list of commands"),
+        }
+
+        true
+    }
+    /// Runs a script in the current environment. Only failing to read it is an
+    /// error here; its syntax and runtime errors are reported like an entry's.
+    fn load(&mut self, file_name: &str) -> io::Result<()> {
+        let code = std::fs::read_to_string(file_name)?;
+
+        match parse(&code) {
+            Ok(program) => self.evaluate(file_name, &code, &program),
+            Err(e) => report(file_name, &code, &e.diagnostics),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `lines` to `repl` one by one and returns what became of each.
+    fn feed(repl: &mut Repl, lines: &[&str]) -> Vec<Line> {
+        lines.iter().map(|a| repl.line(&format!("{a}\n"))).collect()
+    }
+
+    fn value(repl: &mut Repl, code: &str) -> String {
+        match repl.engine.eval(code) {
+            Ok(value) => format!("{value:?}"),
+            Err(_) => panic!("expected a value from {code:?}"),
+        }
+    }
+
+    #[test]
+    fn unclosed_braces_continue_the_entry() {
+        let mut repl = Repl::new();
+
+        assert_eq!(
+            feed(&mut repl, &["func f(a) {", "  if a {", "    return 1", "  }", "  return 2", "}"]),
+            [Line::Continued, Line::Continued, Line::Continued, Line::Continued, Line::Continued, Line::Done]
+        );
+        assert_eq!(value(&mut repl, "f(1 == 1) + f(1 == 2)"), "3");
+    }
+
+    #[test]
+    fn unclosed_parentheses_and_strings_continue_the_entry() {
+        let mut repl = Repl::new();
+
+        assert_eq!(feed(&mut repl, &["let n = (1 +", "2)"]), [Line::Continued, Line::Done]);
+        assert_eq!(value(&mut repl, "n"), "3");
+
+        assert_eq!(feed(&mut repl, &["let s = \"a", "b\""]), [Line::Continued, Line::Done]);
+        assert_eq!(value(&mut repl, "s"), r#""a\nb""#);
+
+        assert_eq!(feed(&mut repl, &["/* a", "*/ let c = 1"]), [Line::Continued, Line::Done]);
+        assert_eq!(value(&mut repl, "c"), "1");
+    }
+
+    #[test]
+    fn the_repl_recovers_after_errors() {
+        let mut repl = Repl::new();
+
+        // A syntax error in the middle of the input is reported at once.
+        assert_eq!(feed(&mut repl, &["let = 1", "let a = 1"]), [Line::Done, Line::Done]);
+
+        // An empty line ends an unfinished entry and reports it.
+        assert_eq!(feed(&mut repl, &["let b = [1,", "", "let b = 2"]), [Line::Continued, Line::Done, Line::Done]);
+        assert!(repl.buffer.is_empty());
+
+        // Runtime errors keep the variables defined before them.
+        assert_eq!(feed(&mut repl, &["let c = 1 / 0", "let d = a + b"]), [Line::Done, Line::Done]);
+        assert_eq!(value(&mut repl, "d"), "3");
+    }
+
+    #[test]
+    fn loading_a_missing_file_is_reported() {
+        let mut repl = Repl::new();
+
+        let e = repl.load("no/such/file.mry").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+
+        assert_eq!(feed(&mut repl, &[":load no/such/file.mry", "let a = 1"]), [Line::Done, Line::Done]);
+        assert_eq!(value(&mut repl, "a"), "1");
+        assert_eq!(feed(&mut repl, &[":quit"]), [Line::Quit]);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::interpreter::{RuntimeError, RuntimeErrorKind, Value};
+
+/// A Rust type that scripts can use as an object: `object.name` reads a property,
+/// `object.name = value` writes one and `object.name(arguments)` calls a method.
+///
+/// Objects are shared with scripts as `Rc<RefCell<T>>`, so the host keeps its own
+/// handle and sees every change a script makes. Only what the implementation
+/// chooses to expose is reachable, and every write goes through `set`.
+///
+/// ```
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// use maaray::{Engine, IntoValue, RuntimeError, ScriptObject, Value, native};
+///
+/// struct Account {
+///     owner: String,
+///     balance: i64,
+/// }
+///
+/// impl ScriptObject for Account {
+///     fn type_name(&self) -> &'static str {
+///         "Account"
+///     }
+///
+///     fn get(&self, name: &str) -> Option<Value> {
+///         match name {
+///             "owner" => Some(self.owner.as_str().into_value()),
+///             "balance" => Some(self.balance.into_value()),
+///             _ => None,
+///         }
+///     }
+///
+///     fn set(&mut self, name: &str, value: Value) -> Result<(), RuntimeError> {
+///         match name {
+///             "owner" => self.owner = native::arguments::<(String,)>("owner", &[value])?.0,
+///             _ => return maaray::script_object::read_only(self, name),
+///         }
+///
+///         Ok(())
+///     }
+///
+///     fn has_method(&self, name: &str) -> bool {
+///         name == "deposit"
+///     }
+///
+///     fn call_method(&mut self, name: &str, arguments: &[Value]) -> Result<Value, RuntimeError> {
+///         let (amount,) = native::arguments::<(i64,)>(name, arguments)?;
+///         self.balance += amount;
+///
+///         Ok(self.balance.into_value())
+///     }
+/// }
+///
+/// let account = Rc::new(RefCell::new(Account { owner: "ada".to_string(), balance: 10 }));
+///
+/// let mut engine = Engine::new();
+/// engine.define("account", Rc::clone(&account).into_value());
+///
+/// engine.eval(r#"account.owner = "grace"; account.deposit(5)"#).unwrap();
+/// assert_eq!(account.borrow().owner, "grace");
+/// assert_eq!(engine.eval("account.balance").unwrap().to_string(), "15");
+///
+/// let error = engine.eval("account.balance = 0").unwrap_err();
+/// assert_eq!(error.to_string(), "1:1: cannot assign to attribute `balance` of `Account`");
+/// ```
+pub trait ScriptObject {
+    /// The type name scripts see in error messages.
+    fn type_name(&self) -> &'static str;
+
+    /// The property `name`, or `None` if there is none.
+    fn get(&self, _name: &str) -> Option<Value> {
+        None
+    }
+
+    /// Changes the property `name`. By default every property is read-only.
+    fn set(&mut self, name: &str, _value: Value) -> Result<(), RuntimeError> {
+        read_only(self, name)
+    }
+
+    /// Whether `name` is a method, checked when a script looks it up.
+    fn has_method(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// Calls the method `name`, which `has_method` accepted.
+    fn call_method(&mut self, name: &str, _arguments: &[Value]) -> Result<Value, RuntimeError> {
+        Err(RuntimeError::new(RuntimeErrorKind::NoAttribute {
+            attribute: name.to_string(),
+            type_name: self.type_name(),
+        }))
+    }
+}
+
+/// The error for assigning to `name` on `object`: read-only if it has such a
+/// property or method, missing otherwise.
+pub fn read_only<T: ScriptObject + ?Sized>(object: &T, name: &str) -> Result<(), RuntimeError> {
+    let type_name = object.type_name();
+    let attribute = name.to_string();
+
+    let kind = if object.get(name).is_some() || object.has_method(name) {
+        RuntimeErrorKind::ReadOnlyAttribute { attribute, type_name }
+    } else {
+        RuntimeErrorKind::NoAttribute { attribute, type_name }
+    };
+
+    Err(RuntimeError::new(kind))
+}
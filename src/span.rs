@@ -0,0 +1,52 @@
+/// A point in source text: byte offset plus 1-based line and column (in characters).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+/// A region of source text from `start` up to, but not including, `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    pub fn line(&self) -> usize {
+        self.start.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.start.column
+    }
+
+    /// A span covering both `self` and `other`, starting where `self` starts.
+    pub fn to(self, other: Span) -> Span {
+        let end = if other.end.offset > self.end.offset {
+            other.end
+        } else {
+            self.end
+        };
+
+        Span {
+            start: self.start,
+            end,
+        }
+    }
+}
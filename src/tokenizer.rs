@@ -1,44 +1,96 @@
+use std::fmt;
+
+use crate::bigint::BigInt;
+use crate::span::{Position, Span};
+
 pub type TResult<T> = Result<T, TokenizerError>;
 
+/// How many interpolated strings may be nested in each other's holes. Each level
+/// is read, lexed and parsed recursively.
+const MAX_INTERPOLATION_DEPTH: usize = 64;
+
 #[derive(Debug, PartialEq)]
 pub enum TokenKind {
     Ident(String),
     StringLiteral(String),
+    /// `$"…{expr}…"`, with the tokens of every `{…}` hole.
+    Interpolated(Vec<TokenPart>),
     Number(String),
     Symbol(char),
+    /// The text after `//`, up to the end of the line.
+    LineComment(String),
+    /// The text between `/*` and `*/`. Block comments nest, and `closed` is false
+    /// if the input ended before the outermost one was closed.
+    BlockComment { text: String, closed: bool },
+    /// Spaces and tabs, only produced by a tokenizer created `with_trivia`.
+    Whitespace,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TokenPart {
+    Literal(String),
+    Hole(Vec<Token>),
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Token {
-    pub(crate) line: usize,
-    pub(crate) column: usize,
+    pub(crate) span: Span,
     pub(crate) kind: TokenKind,
 }
 
 #[derive(Debug)]
 pub struct TokenizerError {
-    line: usize,
-    column: usize,
+    span: Span,
     kind: TokenizerErrorKind,
 }
 
 impl TokenizerError {
-    pub fn line(&self) -> usize { self.line }
-    pub fn column(&self) -> usize { self.column }
+    pub fn span(&self) -> Span { self.span }
+    pub fn kind(&self) -> &TokenizerErrorKind { &self.kind }
 }
 
 #[derive(Debug)]
 pub enum TokenizerErrorKind {
     UnterminatedString,
     InvalidEscapeSequence,
+    InvalidHexEscape,
+    InvalidUnicodeEscape,
+    UnmatchedBrace,
+    /// More than `MAX_INTERPOLATION_DEPTH` interpolated strings inside each other.
+    InterpolationTooDeep,
+    InvalidNumber,
+    NumberOutOfRange,
 }
 
+impl fmt::Display for TokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TokenizerErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            TokenizerErrorKind::InvalidEscapeSequence => write!(f, "invalid escape sequence"),
+            TokenizerErrorKind::InvalidHexEscape => write!(f, "invalid hex escape"),
+            TokenizerErrorKind::InvalidUnicodeEscape => write!(f, "invalid unicode escape"),
+            TokenizerErrorKind::InvalidNumber => write!(f, "invalid number literal"),
+            TokenizerErrorKind::NumberOutOfRange => write!(f, "number literal is too large"),
+            TokenizerErrorKind::InterpolationTooDeep => write!(f, "interpolated strings nested too deeply"),
+            TokenizerErrorKind::UnmatchedBrace => {
+                write!(f, "unmatched `}}` in interpolated string")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TokenizerError {}
+
 #[derive(Clone)]
 pub struct Tokenizer<'a> {
     data: &'a str,
     position: usize,
     line: usize,
     column: usize,
+    /// Whether whitespace other than line breaks becomes tokens instead of being skipped.
+    trivia: bool,
+    /// How many interpolated strings are being read, one inside a hole of the other.
+    interpolations: usize,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -48,13 +100,37 @@ impl<'a> Tokenizer<'a> {
             position: 0,
             line: 1,
             column: 1,
+            trivia: false,
+            interpolations: 0,
+        }
+    }
+
+    /// Also produces `Whitespace` tokens, so that the tokens cover every byte of the input.
+    pub fn with_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
+    fn here(&self) -> Position {
+        Position {
+            offset: self.position,
+            line: self.line,
+            column: self.column,
         }
     }
 
+    fn span_from(&self, start: Position) -> Span {
+        Span::new(start, self.here())
+    }
+
     fn current_char(&self) -> Option<char> {
         self.data[self.position..].chars().next()
     }
 
+    fn peek_char(&self, offset: usize) -> Option<char> {
+        self.data[self.position..].chars().nth(offset)
+    }
+
     fn advance(&mut self, count: usize) {
         for _ in 0..count {
             if let Some(ch) = self.current_char() {
@@ -92,21 +168,424 @@ impl<'a> Tokenizer<'a> {
         ident
     }
 
-    fn read_number(&mut self) -> String {
-        let mut ident = String::new();
+    /// Reads digits of `radix`, dropping `_` and `'` separators.
+    fn read_digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
+
         while let Some(ch) = self.current_char() {
-            if ch.is_numeric() || ch == '\'' {
-                ident.push(ch);
-                self.advance(1);
-            } else {
+            if ch.is_digit(radix) {
+                digits.push(ch);
+            } else if ch != '_' && ch != '\'' {
                 break;
             }
+
+            self.advance(1);
+        }
+
+        digits
+    }
+
+    fn number_error(&self, start: Position, kind: TokenizerErrorKind) -> TokenizerError {
+        TokenizerError {
+            span: self.span_from(start),
+            kind,
+        }
+    }
+
+    /// Reads a numeric literal and returns it in a form `str::parse::<f64>` accepts:
+    /// separators are removed and `0x`/`0b`/`0o` literals are converted to decimal.
+    fn read_number(&mut self) -> TResult<String> {
+        let start = self.here();
+
+        let radix = match (self.current_char(), self.peek_char(1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('b' | 'B')) => 2,
+            (Some('0'), Some('o' | 'O')) => 8,
+            _ => 10,
+        };
+
+        if radix != 10 {
+            self.advance(2);
+
+            let digits = self.read_digits(radix);
+            self.check_number_end(start)?;
+
+            if digits.is_empty() {
+                return Err(self.number_error(start, TokenizerErrorKind::InvalidNumber));
+            }
+
+            // `read_digits` only keeps digits of `radix`, so this always succeeds.
+            return Ok(BigInt::parse_radix(&digits, radix).map_or(digits, |a| a.to_string()));
+        }
+
+        let mut number = self.read_digits(10);
+
+        // A dot followed by another dot is a range, and one followed by a letter is an attribute.
+        if self.current_char() == Some('.') && self.peek_char(1).is_some_and(|a| a.is_ascii_digit()) {
+            self.advance(1);
+
+            number.push('.');
+            number += &self.read_digits(10);
+        }
+
+        if let Some(e @ ('e' | 'E')) = self.current_char() {
+            number.push(e);
+            self.advance(1);
+
+            if let Some(sign @ ('+' | '-')) = self.current_char() {
+                number.push(sign);
+                self.advance(1);
+            }
+
+            let exponent = self.read_digits(10);
+
+            if exponent.is_empty() {
+                self.check_number_end(start)?;
+
+                return Err(self.number_error(start, TokenizerErrorKind::InvalidNumber));
+            }
+
+            number += &exponent;
+        }
+
+        self.check_number_end(start)?;
+
+        // Integers of any size become big integers, but floats stop at `f64::MAX`.
+        if number.contains(['.', 'e', 'E']) && number.parse::<f64>().is_ok_and(f64::is_infinite) {
+            return Err(self.number_error(start, TokenizerErrorKind::NumberOutOfRange));
+        }
+
+        Ok(number)
+    }
+
+    /// Rejects literals running straight into letters, like `12abc` or `0b102`.
+    fn check_number_end(&mut self, start: Position) -> TResult<()> {
+        if !self.current_char().is_some_and(|a| a.is_alphanumeric()) {
+            return Ok(());
+        }
+
+        self.read_identifier();
+
+        Err(self.number_error(start, TokenizerErrorKind::InvalidNumber))
+    }
+
+    fn escape_error(&self, escape: Position, kind: TokenizerErrorKind) -> TokenizerError {
+        TokenizerError {
+            span: self.span_from(escape),
+            kind,
+        }
+    }
+
+    fn read_escape(&mut self) -> TResult<char> {
+        let escape = self.here();
+        self.advance(1); // Skip backslash
+
+        let Some(ch) = self.current_char() else {
+            return Err(self.escape_error(escape, TokenizerErrorKind::InvalidEscapeSequence));
+        };
+
+        self.advance(1);
+
+        match ch {
+            '"' => Ok('"'),
+            '\'' => Ok('\''),
+            '\\' => Ok('\\'),
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            'x' => self.read_hex_escape(escape),
+            'u' => self.read_unicode_escape(escape),
+            _ => Err(self.escape_error(escape, TokenizerErrorKind::InvalidEscapeSequence)),
+        }
+    }
+
+    /// `\x7F`: exactly two hex digits, ASCII only.
+    fn read_hex_escape(&mut self, escape: Position) -> TResult<char> {
+        let mut value = 0;
+
+        for _ in 0..2 {
+            let Some(digit) = self.current_char().and_then(|a| a.to_digit(16)) else {
+                return Err(self.escape_error(escape, TokenizerErrorKind::InvalidHexEscape));
+            };
+
+            value = value * 16 + digit;
+            self.advance(1);
+        }
+
+        if value > 0x7F {
+            return Err(self.escape_error(escape, TokenizerErrorKind::InvalidHexEscape));
+        }
+
+        Ok(char::from(value as u8))
+    }
+
+    /// `\u{1F600}`: one to six hex digits naming a Unicode scalar value.
+    fn read_unicode_escape(&mut self, escape: Position) -> TResult<char> {
+        if self.current_char() != Some('{') {
+            return Err(self.escape_error(escape, TokenizerErrorKind::InvalidUnicodeEscape));
+        }
+
+        self.advance(1);
+
+        let mut digits = String::new();
+
+        loop {
+            match self.current_char() {
+                Some('}') => {
+                    self.advance(1);
+                    break;
+                }
+                Some(ch) if ch.is_ascii_hexdigit() && digits.len() < 6 => {
+                    digits.push(ch);
+                    self.advance(1);
+                }
+                _ => {
+                    return Err(self.escape_error(escape, TokenizerErrorKind::InvalidUnicodeEscape));
+                }
+            }
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.escape_error(escape, TokenizerErrorKind::InvalidUnicodeEscape))
+    }
+
+    /// Skips the rest of a string after an error so the tokenizer resumes after it.
+    fn skip_string(&mut self) {
+        while let Some(ch) = self.current_char() {
+            match ch {
+                '\\' => self.advance(2),
+                '"' => {
+                    self.advance(1);
+                    break;
+                }
+                _ => self.advance(1),
+            }
+        }
+    }
+
+    /// Number of `#`s if a raw string `r"…"` or `r#"…"#` starts at the cursor.
+    fn raw_string_hashes(&self) -> Option<usize> {
+        let rest = self.data[self.position..].strip_prefix('r')?;
+        let hashes = rest.chars().take_while(|a| *a == '#').count();
+
+        rest[hashes..].starts_with('"').then_some(hashes)
+    }
+
+    fn read_raw_string(&mut self, hashes: usize) -> TResult<String> {
+        let start = self.here();
+        self.advance(hashes + 2); // Skip `r`, the hashes and the opening quote
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+
+        let Some(length) = self.data[self.position..].find(&terminator) else {
+            self.advance(self.data[self.position..].chars().count());
+
+            return Err(TokenizerError {
+                span: Span::new(start, start),
+                kind: TokenizerErrorKind::UnterminatedString,
+            });
+        };
+
+        let string = self.data[self.position..self.position + length].to_string();
+        self.advance(string.chars().count() + terminator.len());
+
+        Ok(string)
+    }
+
+    fn read_line_comment(&mut self) -> String {
+        self.advance(2); // Skip `//`
+
+        let length = self.data[self.position..].find('\n').unwrap_or(self.data.len() - self.position);
+        let text = self.data[self.position..self.position + length].to_string();
+        self.advance(text.chars().count());
+
+        text
+    }
+
+    /// Reads a `/* … */` comment, including any comments nested in it.
+    fn read_block_comment(&mut self) -> (String, bool) {
+        self.advance(2); // Skip `/*`
+
+        let start = self.position;
+        let mut depth = 0;
+
+        loop {
+            match (self.current_char(), self.peek_char(1)) {
+                (Some('/'), Some('*')) => {
+                    depth += 1;
+                    self.advance(2);
+                }
+                (Some('*'), Some('/')) if depth == 0 => {
+                    let text = self.data[start..self.position].to_string();
+                    self.advance(2);
+
+                    return (text, true);
+                }
+                (Some('*'), Some('/')) => {
+                    depth -= 1;
+                    self.advance(2);
+                }
+                (Some(_), _) => self.advance(1),
+                (None, _) => return (self.data[start..].to_string(), false),
+            }
+        }
+    }
+
+    fn read_interpolated(&mut self) -> TResult<Vec<TokenPart>> {
+        if self.interpolations == MAX_INTERPOLATION_DEPTH {
+            let start = self.here();
+            self.advance(2); // Skip `$"`
+
+            let span = self.span_from(start);
+            self.skip_interpolated(self.interpolations);
+
+            return Err(TokenizerError {
+                span,
+                kind: TokenizerErrorKind::InterpolationTooDeep,
+            });
+        }
+
+        self.interpolations += 1;
+        let parts = self.read_interpolated_parts();
+        self.interpolations -= 1;
+
+        parts
+    }
+
+    /// Skips the rest of an interpolated string nested too deeply to read, along with
+    /// the `open` strings it sits in, so the tokenizer resumes after the outermost one.
+    /// Keeps a stack instead of recursing, as the nesting may go on for a long time.
+    fn skip_interpolated(&mut self, open: usize) {
+        // Whether the cursor is in a hole, for every string not closed yet.
+        let mut in_hole = vec![true; open];
+        in_hole.push(false);
+
+        while let (Some(&hole), Some(ch)) = (in_hole.last(), self.current_char()) {
+            let next = self.peek_char(1);
+
+            match (hole, ch) {
+                (true, '$') if next == Some('"') => {
+                    in_hole.push(false);
+                    self.advance(2);
+                }
+                (true, '"') => {
+                    self.advance(1);
+                    self.skip_string();
+                }
+                (true, '}') => {
+                    in_hole.pop();
+                    in_hole.push(false);
+                    self.advance(1);
+                }
+                (false, '\\') => self.advance(2),
+                (false, '{' | '}') if next == Some(ch) => self.advance(2),
+                (false, '{') => {
+                    in_hole.pop();
+                    in_hole.push(true);
+                    self.advance(1);
+                }
+                (false, '"') => {
+                    in_hole.pop();
+                    self.advance(1);
+                }
+                _ => self.advance(1),
+            }
+        }
+    }
+
+    fn read_interpolated_parts(&mut self) -> TResult<Vec<TokenPart>> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let start = self.here();
+        self.advance(2); // Skip `$"`
+
+        loop {
+            match self.current_char() {
+                Some('"') => {
+                    self.advance(1);
+                    break;
+                }
+                Some('\\') => match self.read_escape() {
+                    Ok(ch) => literal.push(ch),
+                    Err(e) => {
+                        self.skip_string();
+                        return Err(e);
+                    }
+                },
+                Some(brace @ ('{' | '}')) if self.peek_char(1) == Some(brace) => {
+                    literal.push(brace);
+                    self.advance(2);
+                }
+                Some('{') => {
+                    self.advance(1);
+
+                    if !literal.is_empty() {
+                        parts.push(TokenPart::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(TokenPart::Hole(self.read_hole(start)?));
+                }
+                Some('}') => {
+                    let brace = self.here();
+                    self.advance(1);
+
+                    return Err(TokenizerError {
+                        span: self.span_from(brace),
+                        kind: TokenizerErrorKind::UnmatchedBrace,
+                    });
+                }
+                Some(ch) => {
+                    literal.push(ch);
+                    self.advance(1);
+                }
+                None => {
+                    return Err(TokenizerError {
+                        span: Span::new(start, start),
+                        kind: TokenizerErrorKind::UnterminatedString,
+                    });
+                }
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(TokenPart::Literal(literal));
+        }
+
+        Ok(parts)
+    }
+
+    /// Tokenizes the inside of a `{…}` hole up to its closing brace, which is consumed.
+    fn read_hole(&mut self, string_start: Position) -> TResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let Some(token) = self.next() else {
+                return Err(TokenizerError {
+                    span: Span::new(string_start, string_start),
+                    kind: TokenizerErrorKind::UnterminatedString,
+                });
+            };
+
+            let token = token?;
+
+            match token.kind {
+                TokenKind::Symbol('{') => depth += 1,
+                TokenKind::Symbol('}') if depth == 0 => return Ok(tokens),
+                TokenKind::Symbol('}') => depth -= 1,
+                _ => {}
+            }
+
+            tokens.push(token);
         }
-        ident
     }
 
     fn read_string(&mut self) -> TResult<String> {
         let mut string = String::new();
+        let start = self.here();
         self.advance(1); // Skip opening quote
 
         loop {
@@ -115,39 +594,20 @@ impl<'a> Tokenizer<'a> {
                     self.advance(1);
                     return Ok(string);
                 }
-                Some('\\') => {
-                    self.advance(1);
-                    match self.current_char() {
-                        Some('"') => {
-                            string.push('"');
-                            self.advance(1);
-                        }
-                        Some('\\') => {
-                            string.push('\\');
-                            self.advance(1);
-                        }
-                        Some('n') => {
-                            string.push('\n');
-                            self.advance(1);
-                        }
-                        // Add other escape sequences as needed
-                        _ => {
-                            return Err(TokenizerError {
-                                line: self.line,
-                                column: self.column,
-                                kind: TokenizerErrorKind::InvalidEscapeSequence,
-                            });
-                        }
+                Some('\\') => match self.read_escape() {
+                    Ok(ch) => string.push(ch),
+                    Err(e) => {
+                        self.skip_string();
+                        return Err(e);
                     }
-                }
+                },
                 Some(ch) => {
                     string.push(ch);
                     self.advance(1);
                 }
                 None => {
                     return Err(TokenizerError {
-                        line: self.line,
-                        column: self.column,
+                        span: Span::new(start, start),
                         kind: TokenizerErrorKind::UnterminatedString,
                     });
                 }
@@ -160,37 +620,291 @@ impl Iterator for Tokenizer<'_> {
     type Item = TResult<Token>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let start = self.here();
         self.skip_whitespace();
 
+        if self.trivia && self.position > start.offset {
+            return Some(Ok(Token {
+                span: self.span_from(start),
+                kind: TokenKind::Whitespace,
+            }));
+        }
+
         let ch = self.current_char()?;
+        let start = self.here();
 
-        if ch.is_alphabetic() {
+        if ch == 'r'
+            && let Some(hashes) = self.raw_string_hashes()
+        {
+            Some(self.read_raw_string(hashes).map(|a| Token {
+                span: self.span_from(start),
+                kind: TokenKind::StringLiteral(a),
+            }))
+        } else if ch.is_alphabetic() {
             let ident = self.read_identifier();
             Some(Ok(Token {
-                line: self.line,
-                column: self.column,
+                span: self.span_from(start),
                 kind: TokenKind::Ident(ident),
             }))
-        } else if ch.is_numeric() {
-            let ident = self.read_number();
-            Some(Ok(Token {
-                line: self.line,
-                column: self.column,
-                kind: TokenKind::Number(ident),
+        } else if ch.is_ascii_digit() {
+            Some(self.read_number().map(|a| Token {
+                span: self.span_from(start),
+                kind: TokenKind::Number(a),
+            }))
+        } else if ch == '$' && self.peek_char(1) == Some('"') {
+            Some(self.read_interpolated().map(|a| Token {
+                span: self.span_from(start),
+                kind: TokenKind::Interpolated(a),
             }))
         } else if ch == '"' {
             Some(self.read_string().map(|a| Token {
-                line: self.line,
-                column: self.column,
+                span: self.span_from(start),
                 kind: TokenKind::StringLiteral(a),
             }))
+        } else if ch == '/' && self.peek_char(1) == Some('/') {
+            let text = self.read_line_comment();
+            Some(Ok(Token {
+                span: self.span_from(start),
+                kind: TokenKind::LineComment(text),
+            }))
+        } else if ch == '/' && self.peek_char(1) == Some('*') {
+            let (text, closed) = self.read_block_comment();
+            Some(Ok(Token {
+                span: self.span_from(start),
+                kind: TokenKind::BlockComment { text, closed },
+            }))
         } else {
             self.advance(1);
             Some(Ok(Token {
-                line: self.line,
-                column: self.column,
+                span: self.span_from(start),
                 kind: TokenKind::Symbol(ch),
             }))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(code: &str) -> Vec<TokenKind> {
+        Tokenizer::new(code).map(|a| a.unwrap().kind).collect()
+    }
+
+    fn string(code: &str) -> String {
+        match kinds(code).as_slice() {
+            [TokenKind::StringLiteral(string)] => string.clone(),
+            other => panic!("expected one string in {code:?}, found {other:?}"),
+        }
+    }
+
+    /// The first error in `code`, with the columns it spans.
+    fn error(code: &str) -> (TokenizerErrorKind, usize, usize) {
+        let e = Tokenizer::new(code).find_map(Result::err).expect("expected an error");
+
+        (e.kind, e.span.start.column, e.span.end.column)
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(string(r#""a\"b\\c\'d""#), "a\"b\\c'd");
+        assert_eq!(string(r#""\n\t\r\0""#), "\n\t\r\0");
+        assert_eq!(string(r#""\x41\x7F\x00""#), "A\x7F\0");
+        assert_eq!(string(r#""\u{41}\u{e9}\u{1F600}\u{10FFFF}""#), "Aé😀\u{10FFFF}");
+    }
+
+    #[test]
+    fn invalid_escapes_point_at_the_escape() {
+        assert!(matches!(error(r#""ab\q""#), (TokenizerErrorKind::InvalidEscapeSequence, 4, 6)));
+        assert!(matches!(error(r#""\x4""#), (TokenizerErrorKind::InvalidHexEscape, 2, 5)));
+        assert!(matches!(error(r#""\x80""#), (TokenizerErrorKind::InvalidHexEscape, 2, 6)));
+        assert!(matches!(error(r#""\xZZ""#), (TokenizerErrorKind::InvalidHexEscape, 2, 4)));
+        assert!(matches!(error(r#""\u41""#), (TokenizerErrorKind::InvalidUnicodeEscape, 2, 4)));
+        assert!(matches!(error(r#""\u{}""#), (TokenizerErrorKind::InvalidUnicodeEscape, 2, 6)));
+        assert!(matches!(error(r#""\u{D800}""#), (TokenizerErrorKind::InvalidUnicodeEscape, 2, 10)));
+        assert!(matches!(error(r#""\u{110000}""#), (TokenizerErrorKind::InvalidUnicodeEscape, 2, 12)));
+        assert!(matches!(error(r#""\u{1234567}""#), (TokenizerErrorKind::InvalidUnicodeEscape, 2, 11)));
+        assert!(matches!(error(r#""\u{41""#), (TokenizerErrorKind::InvalidUnicodeEscape, 2, 7)));
+    }
+
+    #[test]
+    fn tokenizing_resumes_after_a_bad_escape() {
+        let tokens: Vec<_> = Tokenizer::new(r#""\q" x"#).collect();
+
+        assert!(tokens[0].is_err());
+        assert_eq!(tokens[1].as_ref().unwrap().kind, TokenKind::Ident("x".to_string()));
+        assert_eq!(tokens.len(), 2);
+    }
+
+    #[test]
+    fn unterminated_strings() {
+        assert!(matches!(error("x = \"abc"), (TokenizerErrorKind::UnterminatedString, 5, 5)));
+        assert!(matches!(error("\"abc\\"), (TokenizerErrorKind::InvalidEscapeSequence, ..)));
+        assert!(matches!(error("r\"abc"), (TokenizerErrorKind::UnterminatedString, 1, 1)));
+        assert!(matches!(error("r#\"abc\""), (TokenizerErrorKind::UnterminatedString, 1, 1)));
+        assert!(matches!(error("$\"a{1"), (TokenizerErrorKind::UnterminatedString, ..)));
+    }
+
+    #[test]
+    fn raw_strings() {
+        assert_eq!(string(r#"r"C:\path\n""#), r"C:\path\n");
+        assert_eq!(string(r##"r#"say "hi""#"##), r#"say "hi""#);
+        assert_eq!(string(r###"r##"a "# b"##"###), r##"a "# b"##);
+        assert_eq!(string("r\"two\nlines\""), "two\nlines");
+        assert_eq!(string(r#"r"""#), "");
+
+        // Without a quote, `r` and `r#` are just an identifier and a symbol.
+        assert_eq!(kinds("r #"), [TokenKind::Ident("r".to_string()), TokenKind::Symbol('#')]);
+    }
+
+    fn number(code: &str) -> String {
+        match kinds(code).as_slice() {
+            [TokenKind::Number(number)] => number.clone(),
+            other => panic!("expected one number in {code:?}, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decimal_numbers() {
+        assert_eq!(number("42"), "42");
+        assert_eq!(number("3.14"), "3.14");
+        assert_eq!(number("1_000_000"), "1000000");
+        assert_eq!(number("1'000"), "1000");
+        assert_eq!(number("1_0.2_5"), "10.25");
+        assert_eq!(number("1e9"), "1e9");
+        assert_eq!(number("2.5E-3"), "2.5E-3");
+        assert_eq!(number("1e+1_0"), "1e+10");
+        assert_eq!(number("1e308"), "1e308");
+    }
+
+    #[test]
+    fn radix_numbers_become_decimal() {
+        assert_eq!(number("0xFF"), "255");
+        assert_eq!(number("0Xff_ff"), "65535");
+        assert_eq!(number("0b1010"), "10");
+        assert_eq!(number("0o17"), "15");
+        assert_eq!(number("0x0"), "0");
+        assert_eq!(number("0x1_0000_0000_0000_0000_0000_0000_0000_0000"), "340282366920938463463374607431768211456");
+    }
+
+    #[test]
+    fn a_dot_without_digits_is_not_part_of_the_number() {
+        let number = |a: &str| TokenKind::Number(a.to_string());
+
+        assert_eq!(kinds("5."), [number("5"), TokenKind::Symbol('.')]);
+        assert_eq!(kinds("1..3"), [number("1"), TokenKind::Symbol('.'), TokenKind::Symbol('.'), number("3")]);
+        assert_eq!(kinds("5.abs"), [number("5"), TokenKind::Symbol('.'), TokenKind::Ident("abs".to_string())]);
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        assert!(matches!(error("0x"), (TokenizerErrorKind::InvalidNumber, 1, 3)));
+        assert!(matches!(error("0x_"), (TokenizerErrorKind::InvalidNumber, 1, 4)));
+        assert!(matches!(error("0b102"), (TokenizerErrorKind::InvalidNumber, 1, 6)));
+        assert!(matches!(error("0o8"), (TokenizerErrorKind::InvalidNumber, 1, 4)));
+        assert!(matches!(error("12abc"), (TokenizerErrorKind::InvalidNumber, 1, 6)));
+        assert!(matches!(error("1e"), (TokenizerErrorKind::InvalidNumber, 1, 3)));
+        assert!(matches!(error("1e+ x"), (TokenizerErrorKind::InvalidNumber, 1, 4)));
+        assert!(matches!(error("1e400"), (TokenizerErrorKind::NumberOutOfRange, 1, 6)));
+        assert!(matches!(error("x = 1.5e309"), (TokenizerErrorKind::NumberOutOfRange, 5, 12)));
+    }
+
+    /// A line and a column.
+    type LineColumn = (usize, usize);
+
+    /// Every token's span as `(byte range, start, end)`.
+    fn spans(code: &str) -> Vec<(std::ops::Range<usize>, LineColumn, LineColumn)> {
+        Tokenizer::new(code)
+            .map(|a| {
+                let Span { start, end } = a.unwrap().span;
+                (start.offset..end.offset, (start.line, start.column), (end.line, end.column))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spans_cover_each_token() {
+        assert_eq!(
+            spans("let abc = 12.5\nx"),
+            [
+                (0..3, (1, 1), (1, 4)),
+                (4..7, (1, 5), (1, 8)),
+                (8..9, (1, 9), (1, 10)),
+                (10..14, (1, 11), (1, 15)),
+                (14..15, (1, 15), (2, 1)),
+                (15..16, (2, 1), (2, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn columns_count_characters_and_offsets_count_bytes() {
+        assert_eq!(
+            spans("é = \"日本\" + x"),
+            [
+                (0..2, (1, 1), (1, 2)),
+                (3..4, (1, 3), (1, 4)),
+                (5..13, (1, 5), (1, 9)),
+                (14..15, (1, 10), (1, 11)),
+                (16..17, (1, 12), (1, 13)),
+            ]
+        );
+
+        assert!(matches!(error("\"😀\\q\""), (TokenizerErrorKind::InvalidEscapeSequence, 3, 5)));
+    }
+
+    #[test]
+    fn multi_line_tokens_end_on_their_last_line() {
+        assert_eq!(spans("\"a\nb\" x"), [(0..5, (1, 1), (2, 3)), (6..7, (2, 4), (2, 5))]);
+        assert_eq!(spans("r\"ü\n\" x"), [(0..6, (1, 1), (2, 2)), (7..8, (2, 3), (2, 4))]);
+        assert_eq!(spans("/* ü\n */x"), [(0..9, (1, 1), (2, 4)), (9..10, (2, 4), (2, 5))]);
+    }
+
+    #[test]
+    fn tokens_in_holes_have_positions_in_the_whole_input() {
+        let Some(TokenKind::Interpolated(parts)) = kinds("é = $\"ö{ab}\"").pop() else {
+            panic!("expected an interpolated string");
+        };
+
+        let [TokenPart::Literal(_), TokenPart::Hole(tokens)] = parts.as_slice() else {
+            panic!("expected a literal and a hole, found {parts:?}");
+        };
+
+        let span = tokens[0].span;
+        assert_eq!((span.start.offset, span.end.offset), (10, 12));
+        assert_eq!((span.start.column, span.end.column), (9, 11));
+    }
+
+    /// `levels` interpolated strings, each in a hole of the one around it.
+    fn interpolated(levels: usize) -> String {
+        (0..levels).fold("1".to_string(), |inner, _| format!("$\"a{{{inner}}}b\""))
+    }
+
+    #[test]
+    fn interpolation_nesting_is_capped() {
+        assert!(matches!(kinds(&interpolated(MAX_INTERPOLATION_DEPTH)).as_slice(), [TokenKind::Interpolated(_)]));
+
+        for levels in [MAX_INTERPOLATION_DEPTH + 1, 10_000] {
+            let code = format!("{} x", interpolated(levels));
+            let tokens: Vec<_> = Tokenizer::new(&code).collect();
+
+            let [Err(e), Ok(x)] = tokens.as_slice() else {
+                panic!("expected an error and then `x`, found {tokens:?}");
+            };
+
+            assert!(matches!(e.kind, TokenizerErrorKind::InterpolationTooDeep));
+            assert_eq!(e.span.start.column, 4 * MAX_INTERPOLATION_DEPTH + 1);
+            assert_eq!(x.kind, TokenKind::Ident("x".to_string()));
+        }
+    }
+
+    #[test]
+    fn skipping_a_deep_string_understands_escapes_and_strings_in_holes() {
+        let inner = interpolated(MAX_INTERPOLATION_DEPTH + 1);
+        let code = format!(r#"$"{{{{ \" {{ "}}" + {inner} }} }}" x"#);
+        let tokens: Vec<_> = Tokenizer::new(&code).collect();
+
+        assert_eq!(tokens.len(), 2, "{tokens:?}");
+        assert_eq!(tokens[1].as_ref().unwrap().kind, TokenKind::Ident("x".to_string()));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::lexer::Lexem;
+
+/// How a grammar rule the parser tried turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Matched,
+    /// The input does not start with this rule, so the parser moves on to the next one.
+    Skipped,
+    Failed,
+}
+
+/// Receives the parser's decisions as it tries each grammar rule in turn.
+pub trait Tracer {
+    /// `rule` is about to be tried at `lexem`, which is `None` at the end of input.
+    fn enter(&mut self, rule: &'static str, lexem: Option<&Lexem>);
+
+    /// The most recently entered rule that has not exited yet finished with `outcome`.
+    fn exit(&mut self, rule: &'static str, outcome: Outcome);
+}
+
+/// Prints the decisions to stderr as an indented tree. A rule that tried nothing
+/// else takes a single line, marked `+` if it matched, `-` if it was skipped and `!`
+/// if it failed. For `let x = 1`:
+///
+/// ```text
+/// - block at `let` 1:1
+/// - if at `let` 1:1
+/// - function at `let` 1:1
+/// ? declaration at `let` 1:1
+///   ? attribute at `1` 1:9
+///     - call at `1` 1:9
+///     - ident at `1` 1:9
+///     - string at `1` 1:9
+///     - interpolated string at `1` 1:9
+///     + number at `1` 1:9
+///   + attribute
+/// + declaration
+/// ```
+#[derive(Default)]
+pub struct TreeTracer {
+    depth: usize,
+    /// The line for the innermost rule entered, held back until we know whether it has children.
+    pending: Option<String>,
+}
+
+impl TreeTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn flush(&mut self) {
+        if let Some(line) = self.pending.take() {
+            eprintln!("{}? {line}", "  ".repeat(self.depth));
+            self.depth += 1;
+        }
+    }
+}
+
+impl Tracer for TreeTracer {
+    fn enter(&mut self, rule: &'static str, lexem: Option<&Lexem>) {
+        self.flush();
+
+        self.pending = Some(match lexem {
+            Some(lexem) => {
+                let span = lexem.span();
+                format!("{rule} at `{}` {}:{}", lexem.token(), span.line(), span.column())
+            }
+            None => format!("{rule} at end of input"),
+        });
+    }
+
+    fn exit(&mut self, rule: &'static str, outcome: Outcome) {
+        let sign = match outcome {
+            Outcome::Matched => '+',
+            Outcome::Skipped => '-',
+            Outcome::Failed => '!',
+        };
+
+        match self.pending.take() {
+            Some(line) => eprintln!("{}{sign} {line}", "  ".repeat(self.depth)),
+            None => {
+                self.depth = self.depth.saturating_sub(1);
+                eprintln!("{}{sign} {rule}", "  ".repeat(self.depth));
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+
+use maaray::parser::{Node, NodeKind, Part};
+
+/// The syntax tree as an indented outline: one node per line with its kind, its
+/// names or value and its span, and the children below it.
+pub fn node(node: &Node) -> String {
+    let mut out = String::new();
+    write_node(&mut out, node, 0);
+
+    out
+}
+
+fn write_node(out: &mut String, node: &Node, depth: usize) {
+    let (label, children) = describe(node);
+    let (start, end) = (node.span.start, node.span.end);
+
+    let _ = writeln!(
+        out,
+        "{:indent$}{label} @ {}:{}..{}:{}",
+        "",
+        start.line,
+        start.column,
+        end.line,
+        end.column,
+        indent = depth * 2
+    );
+
+    if let NodeKind::Interpolated(parts) = &node.kind {
+        for part in parts {
+            match part {
+                Part::Literal(literal) => {
+                    let _ = writeln!(out, "{:indent$}Literal {literal:?}", "", indent = (depth + 1) * 2);
+                }
+                Part::Expression(expression) => write_node(out, expression, depth + 1),
+            }
+        }
+    }
+
+    for child in children {
+        write_node(out, child, depth + 1);
+    }
+}
+
+/// The text after the indentation of `node`'s line, and its children in source order.
+fn describe(node: &Node) -> (String, Vec<&Node>) {
+    if let Some((operator, lhs, rhs)) = node.binary() {
+        return (format!("Binary {}", operator.symbol()), vec![lhs, rhs]);
+    }
+
+    let label = |name: &str, label: &Option<String>| match label {
+        Some(label) => format!("{name} {label}"),
+        None => name.to_string(),
+    };
+
+    match &node.kind {
+        NodeKind::Ident(id) => (format!("Ident {id}"), Vec::new()),
+        NodeKind::Number(nr) => (format!("Number {nr}"), Vec::new()),
+        NodeKind::String(st) => (format!("String {st:?}"), Vec::new()),
+        NodeKind::Interpolated(_) => ("Interpolated".to_string(), Vec::new()),
+        NodeKind::Assignment { name, value, .. } => (format!("Assignment {name}"), vec![value]),
+        NodeKind::Reassignment { name, value } => (format!("Reassignment {name}"), vec![value]),
+        NodeKind::AttributeAssignment {
+            object,
+            attribute,
+            value,
+        } => (format!("AttributeAssignment {attribute}"), vec![object, value]),
+        NodeKind::Function {
+            name,
+            parameters,
+            code,
+            ..
+        } => (format!("Function {name}({})", parameters.join(", ")), vec![code]),
+        NodeKind::Return(value) => ("Return".to_string(), vec![value]),
+        NodeKind::Not(operand) => ("Not".to_string(), vec![operand]),
+        NodeKind::Negate(operand) => ("Negate".to_string(), vec![operand]),
+        NodeKind::Call { callee, arguments } => {
+            ("Call".to_string(), [callee.as_ref()].into_iter().chain(arguments).collect())
+        }
+        NodeKind::AttributeResolve { parent, value } => ("AttributeResolve".to_string(), vec![parent, value]),
+        NodeKind::If {
+            condition,
+            alternative,
+            block,
+        } => ("If".to_string(), vec![condition, block, alternative]),
+        NodeKind::While {
+            label: name,
+            condition,
+            block,
+        } => (label("While", name), vec![condition, block]),
+        NodeKind::For {
+            label: name,
+            variable,
+            iterable,
+            block,
+        } => (format!("{} {variable}", label("For", name)), vec![iterable, block]),
+        NodeKind::Break(name) => (label("Break", name), Vec::new()),
+        NodeKind::Continue(name) => (label("Continue", name), Vec::new()),
+        NodeKind::Array(elements) => ("Array".to_string(), elements.iter().collect()),
+        NodeKind::Program(statements) => ("Program".to_string(), statements.iter().collect()),
+        NodeKind::Block(statements) => ("Block".to_string(), statements.iter().collect()),
+        _ => unreachable!("binary operations are handled above"),
+    }
+}
@@ -0,0 +1,418 @@
+use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+
+use crate::{
+    builtins,
+    bytecode::{Capture, Chunk, Instruction, Prototype},
+    interpreter::{
+        MAX_CALL_DEPTH, RuntimeError, RuntimeErrorKind, Scope, Value, arithmetic, check_arity,
+        compare, get_attribute, iterate, range, set_attribute,
+    },
+    number::Operation,
+};
+
+type Cell = Rc<RefCell<Value>>;
+
+fn new_cell(value: Value) -> Cell {
+    Rc::new(RefCell::new(value))
+}
+
+/// A compiled function together with the variables it captured.
+pub struct Closure {
+    pub prototype: Rc<Prototype>,
+    upvalues: Vec<Cell>,
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// Stack index of the first local slot. The callee sits just below it.
+    base: usize,
+    /// Captured locals, indexed by slot and created when the local is defined.
+    cells: Vec<Cell>,
+    /// Number of iterators open when the call started.
+    iterators: usize,
+}
+
+impl Frame {
+    fn chunk(&self) -> &Chunk {
+        &self.closure.prototype.chunk
+    }
+
+    fn define_cell(&mut self, slot: usize, value: Value) {
+        if self.cells.len() <= slot {
+            self.cells.resize_with(slot + 1, || new_cell(Value::Nil));
+        }
+
+        self.cells[slot] = new_cell(value);
+    }
+
+    /// The cell of `slot`, created holding `nil` if the slot was never defined, which
+    /// only happens in hand-made bytecode.
+    fn cell(&mut self, slot: usize) -> Cell {
+        if self.cells.len() <= slot {
+            self.cells.resize_with(slot + 1, || new_cell(Value::Nil));
+        }
+
+        Rc::clone(&self.cells[slot])
+    }
+}
+
+/// Stack machine running the output of `compiler::compile`.
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    iterators: Vec<Box<dyn Iterator<Item = Value>>>,
+    globals: Scope,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        let mut globals = Scope::default();
+        builtins::install(&mut globals);
+
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            iterators: Vec::new(),
+            globals,
+        }
+    }
+
+    pub fn define_global(&mut self, name: impl Into<String>, value: Value) {
+        self.globals.define(name, value);
+    }
+
+    /// Runs a compiled program and returns the value it returned.
+    pub fn run(&mut self, program: Rc<Prototype>) -> Result<Value, RuntimeError> {
+        self.stack.clear();
+        self.frames.clear();
+        self.iterators.clear();
+
+        let closure = Rc::new(Closure {
+            prototype: program,
+            upvalues: Vec::new(),
+        });
+
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+        self.call(Value::Closure(closure), 0)?;
+
+        loop {
+            let frame = self.frame();
+            let ip = frame.ip;
+            let instruction = frame.chunk().code[ip];
+
+            self.frame_mut().ip += 1;
+
+            match self.step(instruction) {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {}
+                Err(e) => {
+                    // Frames are only popped by `Return`, so the failing one is still on top.
+                    let span = self.frame().chunk().spans[ip];
+
+                    return Err(e.or_span(span));
+                }
+            }
+        }
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("no active frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("no active frame")
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn pop_pair(&mut self) -> (Value, Value) {
+        let rhs = self.pop();
+        let lhs = self.pop();
+
+        (lhs, rhs)
+    }
+
+    fn name(&self, index: usize) -> &String {
+        &self.frame().chunk().names[index]
+    }
+
+    /// Executes one instruction. Returns the program's result once the outermost frame returns.
+    fn step(&mut self, instruction: Instruction) -> Result<Option<Value>, RuntimeError> {
+        match instruction {
+            Instruction::Constant(index) => {
+                let value = self.frame().chunk().constants[index].clone();
+                self.push(value);
+            }
+            Instruction::Nil => self.push(Value::Nil),
+            Instruction::True => self.push(Value::Bool(true)),
+            Instruction::False => self.push(Value::Bool(false)),
+            Instruction::Pop => {
+                self.pop();
+            }
+            Instruction::GetLocal(slot) => {
+                let value = self.stack[self.frame().base + slot].clone();
+                self.push(value);
+            }
+            Instruction::SetLocal(slot) | Instruction::DefineLocal(slot) => {
+                let value = self.pop();
+                let base = self.frame().base;
+
+                self.stack[base + slot] = value;
+            }
+            Instruction::GetCell(slot) => {
+                let value = self.frame_mut().cell(slot).borrow().clone();
+                self.push(value);
+            }
+            Instruction::SetCell(slot) => {
+                let value = self.pop();
+                *self.frame_mut().cell(slot).borrow_mut() = value;
+            }
+            Instruction::DefineCell(slot) => {
+                let value = self.pop();
+                self.frame_mut().define_cell(slot, value);
+            }
+            Instruction::GetUpvalue(index) => {
+                let value = self.frame().closure.upvalues[index].borrow().clone();
+                self.push(value);
+            }
+            Instruction::SetUpvalue(index) => {
+                let value = self.pop();
+                *self.frame().closure.upvalues[index].borrow_mut() = value;
+            }
+            Instruction::GetGlobal(index) => {
+                let name = self.name(index);
+
+                let value = self.globals.get(name).ok_or_else(|| {
+                    RuntimeError::new(RuntimeErrorKind::UndefinedVariable(name.clone()))
+                })?;
+
+                self.push(value);
+            }
+            Instruction::SetGlobal(index) => {
+                let value = self.pop();
+                let name = self.frame().chunk().names[index].clone();
+
+                if !self.globals.assign(&name, value) {
+                    return Err(RuntimeError::new(RuntimeErrorKind::UndefinedVariable(name)));
+                }
+            }
+            Instruction::DefineGlobal(index) => {
+                let value = self.pop();
+                let name = self.name(index).clone();
+
+                self.globals.define(name, value);
+            }
+            Instruction::GetAttribute(index) => {
+                let object = self.pop();
+                let attribute = get_attribute(&object, self.name(index))?;
+
+                self.push(attribute);
+            }
+            Instruction::SetAttribute(index) => {
+                let value = self.pop();
+                let object = self.pop();
+
+                set_attribute(&object, self.name(index), value)?;
+            }
+            Instruction::Arithmetic(operation) => {
+                let value = match self.pop_pair() {
+                    (Value::String(a), Value::String(b)) if operation == Operation::Add => {
+                        Value::String(a + &b)
+                    }
+                    (a, b) => arithmetic(operation, &a, &b)?,
+                };
+
+                self.push(value);
+            }
+            Instruction::Negate => {
+                let value = match self.pop() {
+                    Value::Number(nr) => nr
+                        .negate()
+                        .map(Value::Number)
+                        .map_err(|_| RuntimeError::new(RuntimeErrorKind::Overflow("-")))?,
+                    other => {
+                        return Err(RuntimeError::new(RuntimeErrorKind::InvalidOperand {
+                            operation: "-",
+                            operand: other.type_name(),
+                        }));
+                    }
+                };
+
+                self.push(value);
+            }
+            Instruction::Not => {
+                let value = self.pop();
+                self.push(Value::Bool(!value.is_truthy()));
+            }
+            Instruction::ToBool => {
+                let value = self.pop();
+                self.push(Value::Bool(value.is_truthy()));
+            }
+            Instruction::Equal => {
+                let (lhs, rhs) = self.pop_pair();
+                self.push(Value::Bool(lhs == rhs));
+            }
+            Instruction::NotEqual => {
+                let (lhs, rhs) = self.pop_pair();
+                self.push(Value::Bool(lhs != rhs));
+            }
+            Instruction::Less => self.compare("<", Ordering::is_lt)?,
+            Instruction::Greater => self.compare(">", Ordering::is_gt)?,
+            Instruction::LessEqual => self.compare("<=", Ordering::is_le)?,
+            Instruction::GreaterEqual => self.compare(">=", Ordering::is_ge)?,
+            Instruction::Range => {
+                let (start, end) = self.pop_pair();
+                self.push(range(&start, &end)?);
+            }
+            Instruction::Array(count) => {
+                let items = self.stack.split_off(self.stack.len() - count);
+                self.push(Value::Array(Rc::new(items)));
+            }
+            Instruction::Concat(count) => {
+                let parts = self.stack.split_off(self.stack.len() - count);
+                self.push(Value::String(parts.iter().map(|a| a.to_string()).collect()));
+            }
+            Instruction::Jump(target) => self.frame_mut().ip = target,
+            Instruction::JumpIfFalse(target) => {
+                if !self.pop().is_truthy() {
+                    self.frame_mut().ip = target;
+                }
+            }
+            Instruction::JumpIfTrue(target) => {
+                if self.pop().is_truthy() {
+                    self.frame_mut().ip = target;
+                }
+            }
+            Instruction::IterStart => {
+                let iterable = self.pop();
+                self.iterators.push(iterate(iterable)?);
+            }
+            Instruction::IterNext(target) => {
+                let iterator = self.iterators.last_mut().expect("no active iterator");
+
+                match iterator.next() {
+                    Some(item) => self.push(item),
+                    None => {
+                        self.iterators.pop();
+                        self.frame_mut().ip = target;
+                    }
+                }
+            }
+            Instruction::IterEnd => {
+                self.iterators.pop();
+            }
+            Instruction::Closure(index) => {
+                let frame = self.frame_mut();
+                let prototype = Rc::clone(&frame.chunk().functions[index]);
+
+                let upvalues = prototype
+                    .captures
+                    .iter()
+                    .map(|capture| match capture {
+                        Capture::Local(slot) => frame.cell(*slot),
+                        Capture::Upvalue(index) => Rc::clone(&frame.closure.upvalues[*index]),
+                    })
+                    .collect();
+
+                self.push(Value::Closure(Rc::new(Closure {
+                    prototype,
+                    upvalues,
+                })));
+            }
+            Instruction::Call(count) => {
+                let callee = self.stack[self.stack.len() - count - 1].clone();
+                self.call(callee, count)?;
+            }
+            Instruction::Return => {
+                let value = self.pop();
+                let frame = self.frames.pop().expect("no active frame");
+
+                self.iterators.truncate(frame.iterators);
+                self.stack.truncate(frame.base - 1);
+
+                if self.frames.is_empty() {
+                    return Ok(Some(value));
+                }
+
+                self.push(value);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn compare(&mut self, operation: &'static str, test: fn(Ordering) -> bool) -> Result<(), RuntimeError> {
+        let (lhs, rhs) = self.pop_pair();
+        let value = compare(operation, &lhs, &rhs, test)?;
+
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Calls the callee sitting below its `count` arguments on the stack.
+    /// Natives run to completion, closures push a frame for `run` to execute.
+    fn call(&mut self, callee: Value, count: usize) -> Result<(), RuntimeError> {
+        match callee {
+            Value::Closure(closure) => {
+                let prototype = &closure.prototype;
+                check_arity(&prototype.name, prototype.arity, count)?;
+
+                // The outermost frame is the program itself, not a function call.
+                if self.frames.len() > MAX_CALL_DEPTH {
+                    return Err(RuntimeError::new(RuntimeErrorKind::RecursionLimit));
+                }
+
+                let base = self.stack.len() - count;
+                let mut cells = Vec::new();
+
+                for (slot, captured) in prototype.captured_parameters.iter().enumerate() {
+                    if *captured {
+                        cells.resize_with(slot + 1, || new_cell(Value::Nil));
+                        cells[slot] = new_cell(self.stack[base + slot].clone());
+                    }
+                }
+
+                self.stack.resize(base + prototype.slots, Value::Nil);
+                self.frames.push(Frame {
+                    closure,
+                    ip: 0,
+                    base,
+                    cells,
+                    iterators: self.iterators.len(),
+                });
+            }
+            Value::Native(function) => {
+                if let Some(arity) = function.arity {
+                    check_arity(&function.name, arity, count)?;
+                }
+
+                let arguments = self.stack.split_off(self.stack.len() - count);
+                self.pop();
+
+                let value = (function.function)(&arguments)?;
+                self.push(value);
+            }
+            other => {
+                return Err(RuntimeError::new(RuntimeErrorKind::NotCallable(
+                    other.type_name(),
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
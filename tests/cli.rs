@@ -0,0 +1,504 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Runs the binary with `arguments`, feeding `stdin` to it.
+fn maaray(arguments: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_maaray"))
+        .args(arguments)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+fn status(arguments: &[&str], stdin: &str) -> i32 {
+    maaray(arguments, stdin).status.code().unwrap()
+}
+
+#[test]
+fn exit_status_reflects_the_failing_stage() {
+    assert_eq!(status(&["run", "-"], "println(1)"), 0);
+    assert_eq!(status(&["run", "-"], "let = 1"), 65);
+    assert_eq!(status(&["run", "-"], "\"unterminated"), 65);
+    assert_eq!(status(&["run", "-"], "println(1 / 0)"), 70);
+    assert_eq!(status(&["run", "--vm", "-"], "println(1 / 0)"), 70);
+    assert_eq!(status(&["run", "missing.mry"], ""), 74);
+    assert_eq!(status(&["run", "--unknown", "-"], ""), 64);
+    assert_eq!(status(&["tokens", "-", "extra"], ""), 64);
+}
+
+#[test]
+fn check_reports_undefined_variables() {
+    assert_eq!(status(&["check", "-"], "func f(a) { return a + args }\nprintln(f(1))"), 0);
+
+    let output = maaray(&["check", "-"], "let a = 1\nprintln(a + b)");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr.contains("undefined variable `b`"), "{stderr}");
+}
+
+#[test]
+fn scripts_receive_their_arguments() {
+    for backend in [&["run"][..], &["run", "--vm"]] {
+        let arguments = [backend, &["-", "one", "--two"]].concat();
+        let output = maaray(&arguments, "println(args)");
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "[\"one\", \"--two\"]\n");
+    }
+}
+
+#[test]
+fn parser_trace_goes_to_stderr() {
+    let output = maaray(&["run", "--trace-parser", "-"], "println(1)");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert!(stderr.contains("+ call"), "{stderr}");
+
+    let output = maaray(&["run", "-"], "println(1)");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn formatting_is_stable_and_keeps_meaning() {
+    let source = "let  x=(1+2)*3 ; func f(a,b){ if a<b {return a-(b-1)} else {return -a}}\nprintln(f(x, 10))";
+
+    let formatted = String::from_utf8(maaray(&["fmt", "-"], source).stdout).unwrap();
+    let again = String::from_utf8(maaray(&["fmt", "-"], &formatted).stdout).unwrap();
+
+    assert_eq!(
+        formatted,
+        "let x = (1 + 2) * 3\nfunc f(a, b) {\n    if a < b {\n        return a - (b - 1)\n    } else {\n        return -a\n    }\n}\nprintln(f(x, 10))\n"
+    );
+    assert_eq!(again, formatted);
+    assert_eq!(String::from_utf8_lossy(&maaray(&["run", "-"], &formatted).stdout), "0\n");
+}
+
+#[test]
+fn module_members_cannot_be_reassigned() {
+    for backend in [&["run"][..], &["run", "--vm"]] {
+        let arguments = [backend, &["-"]].concat();
+        let output = maaray(&arguments, "str.from = 1");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(70));
+        assert!(stderr.contains("cannot assign to attribute `from` of `module`"), "{stderr}");
+    }
+
+    let output = maaray(&["run", "-"], "str.from() = 1");
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn check_reports_every_syntax_error() {
+    let source = "let = 1\nprintln(1)\nfunc f(a { return a }\nif x { let = 2 }\n}\nlet y = 3 +";
+    let output = maaray(&["check", "-"], source);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(65));
+
+    for location in ["<stdin>:1:5", "<stdin>:3:10", "<stdin>:4:12", "<stdin>:5:1", "<stdin>:6:11"] {
+        assert!(stderr.contains(location), "{location} missing from {stderr}");
+    }
+
+    assert_eq!(stderr.matches("error:").count(), 5, "{stderr}");
+}
+
+#[test]
+fn unknown_characters_are_reported_not_fatal() {
+    let output = maaray(&["tokens", "-"], "let a = 1 # 2 ? 3");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr.contains("unknown character '#'"), "{stderr}");
+    assert!(stderr.contains("unknown character '?'"), "{stderr}");
+}
+
+#[test]
+fn compound_operators_cannot_be_split() {
+    assert_eq!(status(&["run", "-"], "println(1 == 1, 1 != 2, 1 <= 2, 2 >= 1, 1 || 0, 1 && 1)"), 0);
+
+    for source in ["println(1 = = 1)", "println(1 | | 0)", "println(1 < = 2)", "println(1 ! = 2)"] {
+        assert_eq!(status(&["check", "-"], source), 65, "{source}");
+    }
+}
+
+#[test]
+fn block_comments_nest_and_doc_comments_are_kept() {
+    let source = "/// Adds one.\n/// Really.\nfunc inc(x) { return x /* a /* nested */ comment */ + 1 }\nprintln(inc(1))";
+
+    assert_eq!(String::from_utf8_lossy(&maaray(&["run", "-"], source).stdout), "2\n");
+
+    let ast = String::from_utf8(maaray(&["ast", "--json", "-"], source).stdout).unwrap();
+    assert!(ast.contains(r#""doc":"Adds one.\nReally.""#), "{ast}");
+
+    let formatted = String::from_utf8(maaray(&["fmt", "-"], source).stdout).unwrap();
+    assert!(formatted.starts_with("/// Adds one.\n/// Really.\nfunc inc(x) {\n"), "{formatted}");
+
+    let output = maaray(&["run", "-"], "println(1) /* never /* closed */");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unterminated block comment"));
+}
+
+#[test]
+fn long_runs_of_comments_and_blank_lines_are_lexed() {
+    let source = format!("{}{}println(1)", "// comment\n".repeat(100_000), "\n".repeat(100_000));
+
+    assert_eq!(String::from_utf8_lossy(&maaray(&["run", "-"], &source).stdout), "1\n");
+    assert_eq!(status(&["tokens", "-"], &"/* a */ /// b\n".repeat(100_000)), 0);
+}
+
+#[test]
+fn deep_recursion_is_a_runtime_error() {
+    let output = maaray(&["run", "-"], "func f(n) { return f(n + 1) }\nf(0)");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr.contains("maximum recursion depth exceeded"), "{stderr}");
+    assert!(stderr.contains("<stdin>:1:20"), "{stderr}");
+
+    let sum = |terms| format!("println({})", vec!["1"; terms].join(" + "));
+
+    assert_eq!(String::from_utf8_lossy(&maaray(&["run", "-"], &sum(200)).stdout), "200\n");
+
+    // Recursion a few thousand calls deep, through loops and blocks, is ordinary.
+    let source = "func f(n) { for i in [1] { while 1 == 1 { if n == 0 { return 0 } else { return f(n - 1) + 1 } } } }\nprintln(f(3000))";
+
+    for backend in [&["run", "-"][..], &["run", "--vm", "-"]] {
+        assert_eq!(String::from_utf8_lossy(&maaray(backend, source).stdout), "3000\n");
+    }
+}
+
+#[test]
+fn deep_nesting_is_a_syntax_error() {
+    let nested = |open: &str, inner: &str, close: &str, levels| format!("{}{inner}{}", open.repeat(levels), close.repeat(levels));
+
+    let sources = [
+        nested("{", "", "}", 1000),
+        nested("println(", "1", ")", 3000),
+        nested("println((", "1", "))", 3000),
+        nested("[", "1", "]", 3000),
+        nested("-", "1", "", 3000),
+        format!("println({})", vec!["1"; 3000].join(" + ")),
+        format!("if 1 == 1 {{}}{}", " else if 1 == 1 {}".repeat(3000)),
+        format!("a{}", ".b".repeat(3000)),
+    ];
+
+    for source in &sources {
+        for command in [&["check", "-"][..], &["run", "-"], &["run", "--vm", "-"], &["ast", "-"], &["ast", "--json", "-"], &["fmt", "-"], &["cst", "-"]] {
+            let output = maaray(command, source);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            assert_eq!(output.status.code(), Some(65), "{command:?} on {}", &source[..20]);
+            assert_eq!(stderr.matches("error: nesting too deep").count(), 1, "{stderr}");
+        }
+    }
+
+    let source = format!("let a = 1\n{}\nprintln(a)", nested("{", "let b = 2", "}", 300));
+    let stderr = String::from_utf8_lossy(&maaray(&["check", "-"], &source).stderr).into_owned();
+
+    assert!(stderr.contains("<stdin>:2:257"), "{stderr}");
+    assert_eq!(stderr.matches("error").count(), 1, "{stderr}");
+
+    let output = maaray(&["run", "--vm", "-"], &nested("println(", "1", ")", 200));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), format!("1\n{}", "nil\n".repeat(199)));
+}
+
+#[test]
+fn deeply_nested_interpolation_is_a_syntax_error() {
+    let nested = |levels| (0..levels).fold("1".to_string(), |inner, _| format!("$\"{{{inner}}}\""));
+
+    assert_eq!(String::from_utf8_lossy(&maaray(&["run", "-"], &format!("println({})", nested(64))).stdout), "1\n");
+
+    for command in [&["check", "-"][..], &["run", "-"], &["run", "--vm", "-"], &["fmt", "-"], &["cst", "-"]] {
+        let output = maaray(command, &format!("println({})\nprintln(\"\\q\")", nested(5000)));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(65), "{command:?}");
+        assert_eq!(stderr.matches("error: interpolated strings nested too deeply").count(), 1, "{stderr}");
+        assert!(stderr.contains("<stdin>:2:10"), "the line after it is still read: {stderr}");
+    }
+}
+
+#[test]
+fn both_backends_stop_runaway_recursion_at_the_same_call() {
+    let source = "func f(n) { if n == 0 { return 0 } return 1 + f(n - 1) }\nprintln(f(4999))\nprintln(f(5000))";
+
+    let interpreter = maaray(&["run", "-"], source);
+    let vm = maaray(&["run", "--vm", "-"], source);
+
+    assert_eq!(vm.status.code(), Some(70));
+    assert_eq!(String::from_utf8_lossy(&vm.stdout), "4999\n");
+    assert_eq!(vm.stdout, interpreter.stdout);
+    assert_eq!(vm.stderr, interpreter.stderr);
+    assert_eq!(status(&["run", "--vm", "-"], "func f(n) { return f(n + 1) }\nf(0)"), 70);
+}
+
+#[test]
+fn deep_expressions_are_rejected_before_they_reach_the_compiler() {
+    let sum = |terms| format!("println({})", vec!["1"; terms].join(" + "));
+    let negated = |levels| format!("println({}1)", "-".repeat(levels));
+
+    for source in [sum(1000), sum(10_000), negated(1150)] {
+        for command in [&["run", "--vm", "-"][..], &["compile", "-"], &["disasm", "-"]] {
+            let output = maaray(command, &source);
+
+            assert_eq!(output.status.code(), Some(65), "{command:?}");
+            assert!(String::from_utf8_lossy(&output.stderr).contains("nesting too deep"));
+            assert!(output.stdout.is_empty());
+        }
+    }
+
+    // Just below the limit, both backends agree.
+    for source in [sum(250), negated(250)] {
+        let interpreter = maaray(&["run", "-"], &source);
+        let vm = maaray(&["run", "--vm", "-"], &source);
+
+        assert_eq!(vm.status.code(), Some(0));
+        assert_eq!(vm.stdout, interpreter.stdout);
+        assert_eq!(status(&["compile", "-"], &source), 0);
+    }
+}
+
+#[test]
+fn every_command_handles_the_deepest_input_the_parser_accepts() {
+    let sources = [
+        format!("println({})", vec!["1"; 250].join(" + ")),
+        format!("{}let a = 1{}", "{".repeat(250), "}".repeat(250)),
+        format!("func f(a) {{ return a }}\nprintln({}1{})", "f(".repeat(250), ")".repeat(250)),
+        format!("if 1 == 2 {{}}{} else {{ println(1) }}", " else if 1 == 2 {}".repeat(250)),
+    ];
+
+    for source in &sources {
+        for command in [&["check", "-"][..], &["ast", "--json", "-"], &["ast", "-"], &["fmt", "-"], &["cst", "-"], &["run", "-"], &["run", "--vm", "-"]] {
+            let output = maaray(command, source);
+
+            assert_eq!(output.status.code(), Some(0), "{command:?}: {}", String::from_utf8_lossy(&output.stderr));
+        }
+    }
+}
+
+#[test]
+fn corrupted_bytecode_is_rejected_when_loaded() {
+    let bytes = maaray(&["compile", "-"], "println([1, 2])").stdout;
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("corrupted.mryc");
+
+    let array = bytes.windows(5).position(|a| a == [0x2b, 2, 0, 0, 0]).unwrap();
+    let call = bytes.windows(5).position(|a| a == [0x41, 1, 0, 0, 0]).unwrap();
+
+    // An array, or a call, taking more values than the stack holds.
+    for (at, operand) in [(array, 4), (call, 7)] {
+        let mut corrupted = bytes.clone();
+        corrupted[at + 1] = operand;
+        std::fs::write(&path, corrupted).unwrap();
+
+        let output = maaray(&["run", path.to_str().unwrap()], "");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(65), "{stderr}");
+        assert!(stderr.contains(&format!("at byte {at}: instruction does not fit the stack")), "{stderr}");
+    }
+
+    // `iter_end` without an iterator to finish.
+    let mut corrupted = bytes.clone();
+    corrupted[array..array + 5].copy_from_slice(&[0x35, 0x04, 0x04, 0x01, 0x01]);
+    std::fs::write(&path, corrupted).unwrap();
+
+    assert_eq!(status(&["run", path.to_str().unwrap()], ""), 65);
+}
+
+#[test]
+fn function_parameters_must_be_names() {
+    let output = maaray(&["check", "-"], "func f(1, a + b) { return a }");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr.contains("<stdin>:1:8"), "{stderr}");
+    assert!(stderr.contains("expected identifier, found `1`"), "{stderr}");
+
+    assert_eq!(status(&["check", "-"], "func f(a, b + c) { return a }"), 65);
+    assert_eq!(String::from_utf8_lossy(&maaray(&["run", "-"], "func f(a, b,) { return a - b }\nprintln(f(3, 1))").stdout), "2\n");
+}
+
+#[test]
+fn compile_and_disasm_flags_are_kept_as_aliases() {
+    let source = "let a = 2\nprintln(a * 3)";
+
+    for (flag, command) in [("--disasm", "disasm"), ("--compile", "compile")] {
+        let aliased = maaray(&[flag, "-"], source);
+
+        assert_eq!(aliased.status.code(), Some(0), "{flag}");
+        assert!(!aliased.stdout.is_empty(), "{flag}");
+        assert_eq!(aliased.stdout, maaray(&[command, "-"], source).stdout, "{flag}");
+    }
+
+    assert_eq!(status(&["run", "--disasm", "-"], source), 64);
+}
+
+#[test]
+fn ast_prints_one_line_per_node() {
+    let output = maaray(&["ast", "-"], "func f(a) { return -a }\nprintln(f(1) + 2)");
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Program @ 1:1..2:18\n  Function f(a) @ 1:1..1:24\n    Block @ 1:11..1:24\n      Return @ 1:13..1:22\n        Negate @ 1:20..1:22\n          Ident a @ 1:21..1:22\n  Call @ 2:1..2:18\n    Ident println @ 2:1..2:8\n    Binary + @ 2:9..2:17\n      Call @ 2:9..2:13\n        Ident f @ 2:9..2:10\n        Number 1 @ 2:11..2:12\n      Number 2 @ 2:16..2:17\n"
+    );
+
+    let sum = format!("println({})", vec!["1"; 200].join(" + "));
+    let output = maaray(&["ast", "-"], &sum);
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 401);
+    assert!(output.stdout.len() < 300_000, "{} bytes", output.stdout.len());
+}
+
+#[test]
+fn radix_literals_grow_into_big_integers_and_floats_must_stay_finite() {
+    let big = maaray(&["run", "-"], "println(0x1_0000_0000_0000_0000_0000_0000_0000_0000)\nprintln(0b1 + 0o17)");
+    assert_eq!(String::from_utf8_lossy(&big.stdout), "340282366920938463463374607431768211456\n16\n");
+
+    let vm = maaray(&["run", "--vm", "-"], "println(0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_F)");
+    assert_eq!(String::from_utf8_lossy(&vm.stdout), "5444517870735015415413993718908291383295\n");
+
+    let overflow = maaray(&["run", "-"], "println(1.5e400)");
+    assert_eq!(overflow.status.code(), Some(65));
+    assert!(String::from_utf8_lossy(&overflow.stderr).contains("number literal is too large"));
+
+    assert_eq!(status(&["run", "-"], "println(1e308)"), 0);
+}
+
+#[test]
+fn both_backends_point_runtime_errors_at_the_same_span() {
+    let programs = [
+        "println(1)\nlet x = int.from(1e30)",
+        "let a = [1, 2]\nprintln(a.missing)",
+        "let a = [1, 2]\nprintln(a.len().missing())",
+        "func f(a) { return a }\nprintln(1 + f(1, 2))",
+        "println(  len(1)  )",
+        "let s = \"abc\"\nprintln(s.upper(1))",
+    ];
+
+    for program in programs {
+        let interpreter = maaray(&["run", "-"], program);
+        let vm = maaray(&["run", "--vm", "-"], program);
+
+        assert_eq!(interpreter.status.code(), Some(70), "{program}");
+        assert_eq!(vm.status.code(), Some(70), "{program}");
+        assert_eq!(String::from_utf8_lossy(&vm.stderr), String::from_utf8_lossy(&interpreter.stderr), "{program}");
+    }
+}
+
+#[test]
+fn every_branch_of_an_else_if_chain_is_taken() {
+    let source = "func sign(n) { if n < 0 { return \"negative\" } else if n == 0 { return \"zero\" } else { return \"positive\" } }\nprintln(sign(-5), sign(0), sign(5))\nif 1 == 2 { println(1) } else if 2 == 3 { println(2) }\nprintln(\"done\")";
+
+    for backend in [&["run", "-"][..], &["run", "--vm", "-"]] {
+        let output = maaray(backend, source);
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "negative zero positive\ndone\n", "{backend:?}");
+    }
+}
+
+#[test]
+fn loops_skip_and_leave_by_label() {
+    let source = "outer: for i in 0..3 {\n    for j in 0..3 {\n        if j == 1 { continue outer }\n        println(i, j)\n    }\n}\n\
+        outer: while 1 == 1 {\n    for c in \"xy\" {\n        if c == \"y\" { break outer }\n        println(c)\n    }\n}\n\
+        for x in [1, 2, 3, 4] {\n    if x % 2 == 0 { continue }\n    println(x)\n}\n\
+        for c in \"hé!\" { println(c) }";
+
+    for backend in [&["run", "-"][..], &["run", "--vm", "-"]] {
+        let output = maaray(backend, source);
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "0 0\n1 0\n2 0\nx\n1\n3\nh\né\n!\n", "{backend:?}");
+    }
+}
+
+#[test]
+fn doubled_braces_in_interpolated_strings_are_literal() {
+    let source = "println($\"a {{b}} {1+2}\")\nprintln($\"{{{\"x\"}}}\", $\"}}{{\")";
+
+    for backend in [&["run", "-"][..], &["run", "--vm", "-"]] {
+        let output = maaray(backend, source);
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a {b} 3\n{x} }{\n", "{backend:?}");
+    }
+
+    let output = maaray(&["run", "-"], "println($\"a } b\")");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unmatched `}` in interpolated string"));
+}
+
+#[test]
+fn integer_overflow_is_a_runtime_error() {
+    for (source, operator) in [("println(9223372036854775807 + 1)", "+"), ("println((-9223372036854775807 - 1) / -1)", "/")] {
+        for backend in [&["run", "-"][..], &["run", "--vm", "-"]] {
+            let output = maaray(backend, source);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            assert_eq!(output.status.code(), Some(70), "{backend:?} {source}");
+            assert!(stderr.contains(&format!("integer overflow in `{operator}`")), "{stderr}");
+            assert!(output.stdout.is_empty());
+        }
+    }
+}
+
+#[test]
+fn operators_bind_by_precedence_and_associate_left() {
+    let source = "println(1 - 2 - 3, 8 / 2 / 2, 7 % 4 % 2)\n\
+        println(2 + 3 * 4 < 15 == 1 < 2, 1 < 2 && 3 < 4, 1 == 1 || 1 / 0 == 1, 1 || 0 && 0)\n\
+        println(4 | 6 & 3, 1 | 2 && 0, 4 & 6 || 0)";
+
+    for backend in [&["run", "-"][..], &["run", "--vm", "-"]] {
+        let output = maaray(backend, source);
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "-4 2 1\ntrue true true true\n6 false true\n", "{backend:?}");
+    }
+
+    // `|` binds looser than `==`, so this is `1 | (2 == 3)`.
+    let output = maaray(&["run", "-"], "println(1 | 2 == 3)");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot apply `|` to `int` and `bool`"));
+}
+
+#[test]
+fn the_repl_finishes_an_entry_left_open_at_the_end_of_the_input() {
+    let output = maaray(&[], "println(1 +\n2)");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), ">> .. 3\n>> \n");
+
+    let output = maaray(&[], "println(1");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(stderr.contains("unclosed argument list"), "{stderr}");
+    assert!(stderr.contains("<repl>:1:9"), "{stderr}");
+}
+
+#[test]
+fn a_closed_output_pipe_ends_the_program_quietly() {
+    let source = "println(1)\n".repeat(10_000);
+
+    for command in [&["run", "-"][..], &["run", "--vm", "-"], &["tokens", "-"], &["cst", "-"], &["ast", "-"], &["fmt", "-"], &[]] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_maaray"))
+            .args(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // Nobody reads the output, so the first write fails with a broken pipe, and the
+        // program may stop before it has read all of its input.
+        drop(child.stdout.take());
+        child.stdin.take().unwrap().write_all(source.as_bytes()).ok();
+
+        let output = child.wait_with_output().unwrap();
+
+        assert_eq!(output.status.code(), Some(0), "{command:?}");
+        assert!(output.stderr.is_empty(), "{command:?}: {}", String::from_utf8_lossy(&output.stderr));
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Expected standard output of every script in `maaray-examples`.
+const EXPECTED: &[(&str, &str)] = &[
+    ("arithmetics.mry", "6\n8\n"),
+    ("escaped_strings.mry", "\"This string is escaped.\""),
+    ("fibonacci.mry", "Fibonacci of 10 is: 55\n"),
+    ("fibonacci_advanced.mry", "Fibonacci of 10 is: 55\n"),
+    ("hello_world.mry", "Hello, world!\n"),
+    ("loops.mry", "45\n1\n2\n3\n"),
+    (
+        "numbers.mry",
+        "3 1 3.5\n1000000 255 10\n1267650600228229401496703205376\n",
+    ),
+];
+
+fn examples() -> Vec<(PathBuf, &'static str)> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("maaray-examples");
+
+    fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap();
+
+            let Some((_, expected)) = EXPECTED.iter().find(|(example, _)| *example == name) else {
+                panic!("no expected output recorded for {name}");
+            };
+
+            (path, *expected)
+        })
+        .collect()
+}
+
+fn run(flags: &[&str], path: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_maaray"))
+        .args(flags)
+        .arg(path)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{} failed with {flags:?}:\n{}",
+        path.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn run_examples(flags: &[&str]) {
+    for (path, expected) in examples() {
+        let stdout = run(flags, &path);
+
+        assert_eq!(stdout, expected, "{} printed unexpected output with {flags:?}", path.display());
+    }
+}
+
+#[test]
+fn interpreter_runs_examples() {
+    run_examples(&["run"]);
+}
+
+#[test]
+fn vm_runs_examples() {
+    run_examples(&["run", "--vm"]);
+}
+
+#[test]
+fn compiled_examples_run_without_source() {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("compiled-examples");
+    fs::create_dir_all(&directory).unwrap();
+
+    for (path, expected) in examples() {
+        let source = directory.join(path.file_name().unwrap());
+        fs::copy(&path, &source).unwrap();
+
+        run(&["compile"], &source);
+
+        let stdout = run(&["run"], &source.with_extension("mryc"));
+
+        assert_eq!(stdout, expected, "{} printed unexpected output", path.display());
+    }
+}
+
+#[test]
+fn lossless_tree_reprints_examples() {
+    for (path, _) in examples() {
+        let source = fs::read_to_string(&path).unwrap();
+        let tree = maaray::parse_lossless(&source).unwrap();
+
+        assert_eq!(tree.to_string(), source, "{} did not round-trip", path.display());
+    }
+}